@@ -1,6 +1,7 @@
 use std::mem::swap;
+use glam::Mat4;
 use crate::core::ray::Ray;
-use crate::core::rehnda_math::Point3f;
+use crate::core::rehnda_math::{Point3f, Vec3f};
 
 #[derive(Debug, Copy, Clone)]
 pub struct Aabb {
@@ -30,6 +31,25 @@ impl Aabb {
         true
     }
 
+    /// Slab test for traversals that test many boxes against the same ray, taking the reciprocal
+    /// of the ray direction precomputed by the caller
+    pub fn does_hit_with_inverse_direction(&self, ray: &Ray, inverse_direction: &Vec3f, t_min: f32, t_max: f32) -> bool {
+        let t_0 = (self.min_corner - ray.origin) * *inverse_direction;
+        let t_1 = (self.max_corner - ray.origin) * *inverse_direction;
+        let t_near = t_0.min(t_1).max_element().max(t_min);
+        let t_far = t_0.max(t_1).min_element().min(t_max);
+        t_near <= t_far
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let extent = self.max_corner - self.min_corner;
+        2.0 * (extent.x * extent.y + extent.y * extent.z + extent.z * extent.x)
+    }
+
+    pub fn centroid(&self) -> Point3f {
+        0.5 * (self.min_corner + self.max_corner)
+    }
+
     pub fn surrounding_box(box_0: &Aabb, box_1: &Aabb) -> Aabb {
         let min_corner = Point3f::new(
             box_0.min_corner.x.min(box_1.min_corner.x),
@@ -46,5 +66,28 @@ impl Aabb {
             max_corner,
         }
     }
-}
 
+    /// Axis aligned box enclosing this box after it has been transformed by `matrix`
+    pub fn transformed(&self, matrix: &Mat4) -> Aabb {
+        let mut min_corner = Point3f::splat(f32::MAX);
+        let mut max_corner = Point3f::splat(f32::MIN);
+        for i in 0..2 {
+            for j in 0..2 {
+                for k in 0..2 {
+                    let corner = Point3f::new(
+                        if i == 0 { self.min_corner.x } else { self.max_corner.x },
+                        if j == 0 { self.min_corner.y } else { self.max_corner.y },
+                        if k == 0 { self.min_corner.z } else { self.max_corner.z },
+                    );
+                    let transformed = matrix.transform_point3a(corner);
+                    min_corner = min_corner.min(transformed);
+                    max_corner = max_corner.max(transformed);
+                }
+            }
+        }
+        Aabb {
+            min_corner,
+            max_corner,
+        }
+    }
+}
@@ -1,10 +1,72 @@
 use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
 use std::sync::Arc;
+use serde::Deserialize;
 use crate::acceleration::aabb::Aabb;
+use crate::acceleration::flat_bvh::FlatBvh;
 use crate::core::ray::Ray;
-use crate::core::rehnda_math::random_int_in_range;
+use crate::core::rehnda_math::{random_int_in_range, Rng, seeded_rng};
 use crate::hittable::{HitResult, Hittable};
 
+/// Number of buckets centroids are sorted into along each axis when evaluating SAH splits
+const SAH_BIN_COUNT: usize = 12;
+/// Cost of traversing an interior node relative to intersecting a primitive
+pub const SAH_TRAVERSAL_COST: f32 = 0.125;
+pub const SAH_INTERSECTION_COST: f32 = 1.0;
+/// Seeds the random split axes of the median builder, so the same objects always build the same tree
+pub(crate) const MEDIAN_SEED: u64 = 0;
+
+#[derive(Debug, Copy, Clone, Deserialize, Default, PartialEq, Eq)]
+pub enum BvhBuilder {
+    /// Sorts along a random axis and splits the objects in half
+    Median,
+    /// Binned surface area heuristic, picks the split minimising the expected cost of a ray query
+    #[default]
+    Sah,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Default, PartialEq, Eq)]
+pub enum BvhLayout {
+    /// Binary tree of [BvhNode]s linked by reference counted pointers
+    Tree,
+    /// Contiguous array of nodes with multi-primitive leaves, see [FlatBvh]
+    #[default]
+    Flat,
+}
+
+/// Shape of a built BVH, useful for comparing builders
+#[derive(Debug, Copy, Clone, Default)]
+pub struct BvhStats {
+    pub num_primitives: usize,
+    pub num_interior_nodes: usize,
+    pub num_leaves: usize,
+    pub max_depth: usize,
+    /// Expected cost of a ray query relative to intersecting a single primitive, see [SAH_TRAVERSAL_COST]
+    pub sah_cost: f32,
+}
+
+impl Display for BvhStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} primitives, {} interior nodes, {} leaves, max depth {}, SAH cost {:.2}",
+               self.num_primitives, self.num_interior_nodes, self.num_leaves, self.max_depth, self.sah_cost)
+    }
+}
+
+/// Builds a BVH over `objects` with the given builder and memory layout
+pub fn build_bvh(objects: &[Arc<dyn Hittable>], time_0: f32, time_1: f32, builder: BvhBuilder, layout: BvhLayout) -> (Arc<dyn Hittable>, BvhStats) {
+    match layout {
+        BvhLayout::Tree => match BvhNode::build(objects, time_0, time_1, builder) {
+            (Some(bvh), stats) => (Arc::new(bvh), stats),
+            // an empty list hits nothing, as an empty flat BVH does
+            (None, stats) => (Arc::new(Vec::<Arc<dyn Hittable>>::new()), stats),
+        },
+        BvhLayout::Flat => {
+            let (bvh, stats) = FlatBvh::build(objects, time_0, time_1, builder);
+            (Arc::new(bvh), stats)
+        }
+    }
+}
+
 pub struct BvhNode {
     pub left: Arc<dyn Hittable>,
     pub right: Arc<dyn Hittable>,
@@ -12,36 +74,44 @@ pub struct BvhNode {
 }
 
 impl BvhNode {
-    pub fn new(src_objects: &[Arc<dyn Hittable>], time_0: f32, time_1: f32) -> BvhNode {
+    /// Builds a BVH over `src_objects`, also returning statistics about the shape of the tree. There
+    /// is no tree when `src_objects` is empty.
+    pub fn build(src_objects: &[Arc<dyn Hittable>], time_0: f32, time_1: f32, builder: BvhBuilder) -> (Option<BvhNode>, BvhStats) {
         let mut objects = src_objects.to_vec();
-
-        let split_axis = random_int_in_range(0, 2);
-        let comparator_func = match split_axis {
-            0 => BvhNode::box_x_compare,
-            1 => BvhNode::box_y_compare,
-            2 => BvhNode::box_z_compare,
-            _ => unreachable!(),
+        let mut stats = BvhStats {
+            num_primitives: objects.len(),
+            ..BvhStats::default()
         };
+        if objects.is_empty() {
+            return (None, stats);
+        }
+        let mut rng = seeded_rng(MEDIAN_SEED);
+        let root = BvhNode::build_node(&mut objects, time_0, time_1, builder, &mut rng, 1, &mut stats);
 
-        let objects_span = src_objects.len();
-        let (left, right) = match objects_span {
-            1 => (objects[0].clone(), objects[0].clone()),
-            2 => unsafe {
-                if comparator_func(objects.get_unchecked(0), objects.get_unchecked(1)) == Ordering::Greater {
-                    (objects[0].clone(), objects[1].clone())
-                } else {
-                    (objects[1].clone(), objects[0].clone())
-                }
-            },
+        let root_area = root.node_bounding_box.surface_area();
+        if root_area > 0.0 {
+            stats.sah_cost /= root_area;
+        }
+        (Some(root), stats)
+    }
+
+    fn build_node(objects: &mut [Arc<dyn Hittable>], time_0: f32, time_1: f32, builder: BvhBuilder, rng: &mut Rng, depth: usize, stats: &mut BvhStats) -> BvhNode {
+        let (left, right) = match objects.len() {
+            1 => {
+                BvhNode::record_leaf(&objects[0], time_0, time_1, stats);
+                (objects[0].clone(), objects[0].clone())
+            }
+            2 => {
+                BvhNode::record_leaf(&objects[0], time_0, time_1, stats);
+                BvhNode::record_leaf(&objects[1], time_0, time_1, stats);
+                (objects[0].clone(), objects[1].clone())
+            }
             _ => {
-                // TODO only sort slice
-                objects.sort_by(comparator_func);
-                let mid = src_objects.len() / 2;
-                let left_slice = &objects[0..mid];
-                let right_slice = &objects[mid..objects_span];
+                let (mid, _) = BvhNode::partition(objects, time_0, time_1, builder, rng);
+                let (left_slice, right_slice) = objects.split_at_mut(mid);
                 (
-                    Arc::new(BvhNode::new(left_slice, time_0, time_1)) as Arc<dyn Hittable>,
-                    Arc::new(BvhNode::new(right_slice, time_0, time_1)) as Arc<dyn Hittable>
+                    BvhNode::build_child(left_slice, time_0, time_1, builder, rng, depth + 1, stats),
+                    BvhNode::build_child(right_slice, time_0, time_1, builder, rng, depth + 1, stats),
                 )
             }
         };
@@ -52,6 +122,10 @@ impl BvhNode {
             } else { unreachable!() }
         } else { unreachable!() };
 
+        stats.num_interior_nodes += 1;
+        stats.max_depth = stats.max_depth.max(depth);
+        stats.sah_cost += SAH_TRAVERSAL_COST * bounding_box.surface_area();
+
         BvhNode {
             left,
             right,
@@ -59,6 +133,127 @@ impl BvhNode {
         }
     }
 
+    /// Single objects are used directly as a child rather than wrapped in a node of their own
+    fn build_child(objects: &mut [Arc<dyn Hittable>], time_0: f32, time_1: f32, builder: BvhBuilder, rng: &mut Rng, depth: usize, stats: &mut BvhStats) -> Arc<dyn Hittable> {
+        if objects.len() == 1 {
+            BvhNode::record_leaf(&objects[0], time_0, time_1, stats);
+            objects[0].clone()
+        } else {
+            Arc::new(BvhNode::build_node(objects, time_0, time_1, builder, rng, depth, stats))
+        }
+    }
+
+    fn record_leaf(object: &Arc<dyn Hittable>, time_0: f32, time_1: f32, stats: &mut BvhStats) {
+        stats.num_leaves += 1;
+        stats.sah_cost += SAH_INTERSECTION_COST * BvhNode::object_box(object, time_0, time_1).surface_area();
+    }
+
+    /// Reorders the objects into the two halves chosen by `builder`, returning the index to split
+    /// them at and, for SAH splits, the area weighted cost of the split. `rng` picks the axis of
+    /// median splits.
+    pub(crate) fn partition(objects: &mut [Arc<dyn Hittable>], time_0: f32, time_1: f32, builder: BvhBuilder, rng: &mut Rng) -> (usize, Option<f32>) {
+        match builder {
+            BvhBuilder::Median => (BvhNode::partition_median(objects, rng), None),
+            BvhBuilder::Sah => BvhNode::partition_sah(objects, time_0, time_1),
+        }
+    }
+
+    /// Sorts the objects along a random axis, returning the index to split them at
+    fn partition_median(objects: &mut [Arc<dyn Hittable>], rng: &mut Rng) -> usize {
+        let split_axis = random_int_in_range(rng, 0, 2);
+        let comparator_func = match split_axis {
+            0 => BvhNode::box_x_compare,
+            1 => BvhNode::box_y_compare,
+            2 => BvhNode::box_z_compare,
+            _ => unreachable!(),
+        };
+
+        objects.sort_by(comparator_func);
+        objects.len() / 2
+    }
+
+    /// Partitions the objects at the binned SAH split with the lowest cost. Falls back to a median
+    /// split when all the centroids coincide or there are no objects.
+    fn partition_sah(objects: &mut [Arc<dyn Hittable>], time_0: f32, time_1: f32) -> (usize, Option<f32>) {
+        let boxes: Vec<Aabb> = objects.iter().map(|object| BvhNode::object_box(object, time_0, time_1)).collect();
+        let Some(centroid_bounds) = boxes.iter()
+            .map(|object_box| Aabb { min_corner: object_box.centroid(), max_corner: object_box.centroid() })
+            .reduce(|a, b| Aabb::surrounding_box(&a, &b)) else {
+            return (0, None);
+        };
+        let centroid_extent = centroid_bounds.max_corner - centroid_bounds.min_corner;
+
+        let bin_index = |object_box: &Aabb, axis: usize| -> usize {
+            let offset = (object_box.centroid()[axis] - centroid_bounds.min_corner[axis]) / centroid_extent[axis];
+            ((offset * SAH_BIN_COUNT as f32) as usize).min(SAH_BIN_COUNT - 1)
+        };
+
+        let mut best_split: Option<(usize, usize, f32)> = None;
+        for axis in 0..3 {
+            if centroid_extent[axis] <= 0.0 {
+                continue;
+            }
+
+            let mut bin_counts = [0usize; SAH_BIN_COUNT];
+            let mut bin_boxes: [Option<Aabb>; SAH_BIN_COUNT] = [None; SAH_BIN_COUNT];
+            for object_box in &boxes {
+                let bin = bin_index(object_box, axis);
+                bin_counts[bin] += 1;
+                bin_boxes[bin] = Some(bin_boxes[bin].map_or(*object_box, |bin_box| Aabb::surrounding_box(&bin_box, object_box)));
+            }
+
+            // sweep from the right first so the left sweep can evaluate each split in one pass
+            let mut right_costs = [0.0f32; SAH_BIN_COUNT];
+            let mut right_box: Option<Aabb> = None;
+            let mut right_count = 0;
+            for split in (1..SAH_BIN_COUNT).rev() {
+                right_count += bin_counts[split];
+                right_box = BvhNode::merge_boxes(right_box, bin_boxes[split]);
+                right_costs[split] = right_box.map_or(0.0, |b| b.surface_area()) * right_count as f32;
+            }
+
+            let mut left_box: Option<Aabb> = None;
+            let mut left_count = 0;
+            for split in 1..SAH_BIN_COUNT {
+                left_count += bin_counts[split - 1];
+                left_box = BvhNode::merge_boxes(left_box, bin_boxes[split - 1]);
+                if left_count == 0 || left_count == boxes.len() {
+                    continue;
+                }
+                let cost = left_box.map_or(0.0, |b| b.surface_area()) * left_count as f32 + right_costs[split];
+                if best_split.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                    best_split = Some((axis, split, cost));
+                }
+            }
+        }
+
+        match best_split {
+            Some((axis, split, cost)) => {
+                let (left_objects, right_objects): (Vec<_>, Vec<_>) = objects.iter().zip(boxes.iter())
+                    .partition(|(_, object_box)| bin_index(object_box, axis) < split);
+                let mid = left_objects.len();
+                let partitioned: Vec<Arc<dyn Hittable>> = left_objects.into_iter().chain(right_objects)
+                    .map(|(object, _)| object.clone())
+                    .collect();
+                objects.clone_from_slice(&partitioned);
+                (mid, Some(cost))
+            }
+            None => (objects.len() / 2, None),
+        }
+    }
+
+    fn merge_boxes(a: Option<Aabb>, b: Option<Aabb>) -> Option<Aabb> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(Aabb::surrounding_box(&a, &b)),
+            (a, None) => a,
+            (None, b) => b,
+        }
+    }
+
+    pub(crate) fn object_box(object: &Arc<dyn Hittable>, time_0: f32, time_1: f32) -> Aabb {
+        object.bounding_box(time_0, time_1).expect("Can't build a BVH over objects without bounding boxes")
+    }
+
     fn box_x_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>) -> Ordering {
         BvhNode::box_compare(a, b, 0)
     }
@@ -82,7 +277,7 @@ impl BvhNode {
 }
 
 impl Hittable for BvhNode {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult<'_>> {
         if !self.node_bounding_box.does_hit(ray, t_min, t_max) {
             return None;
         }
@@ -102,4 +297,24 @@ impl Hittable for BvhNode {
     fn bounding_box(&self, _time_0: f32, _time_1: f32) -> Option<Aabb> {
         Some(self.node_bounding_box)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::rehnda_math::Vec3f;
+
+    #[test]
+    fn empty_inputs_build_bvhs_that_hit_nothing() {
+        let ray = Ray { origin: Vec3f::ZERO, direction: Vec3f::X, time: 0.0 };
+        for builder in [BvhBuilder::Median, BvhBuilder::Sah] {
+            for layout in [BvhLayout::Tree, BvhLayout::Flat] {
+                let (bvh, stats) = build_bvh(&[], 0.0, 1.0, builder, layout);
+                assert!(bvh.hit(&ray, 0.001, f32::MAX).is_none());
+                assert!(bvh.bounding_box(0.0, 1.0).is_none());
+                assert_eq!(stats.num_primitives, 0);
+            }
+            assert_eq!(BvhNode::partition(&mut [], 0.0, 1.0, builder, &mut seeded_rng(MEDIAN_SEED)), (0, None));
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::sync::Arc;
+use crate::acceleration::aabb::Aabb;
+use crate::acceleration::bvh::{BvhBuilder, BvhNode, BvhStats, MEDIAN_SEED, SAH_INTERSECTION_COST, SAH_TRAVERSAL_COST};
+use crate::core::ray::Ray;
+use crate::core::rehnda_math::{Rng, seeded_rng, Vec3f};
+use crate::hittable::{HitResult, Hittable};
+
+/// Most primitives a leaf will hold, leaves with fewer are made when the SAH says splitting is
+/// not worth the extra traversal step
+const MAX_LEAF_PRIMITIVES: usize = 4;
+/// Size of the traversal stack. Nodes at this depth are made leaves with all their primitives, so
+/// the stack never holds more than the interior nodes above them.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Copy, Clone)]
+struct FlatBvhNode {
+    bounding_box: Aabb,
+    /// Index of the first primitive for leaves, or of the second child for interior nodes. The
+    /// first child of an interior node is always stored directly after it.
+    offset: u32,
+    /// Zero for interior nodes
+    num_primitives: u32,
+    /// Axis the children are separated along, used to visit the nearer child first
+    split_axis: u8,
+    /// Whether the first child's centroid is below the second's along `split_axis`
+    first_is_lower: bool,
+}
+
+/// BVH stored as a contiguous array of nodes in depth first order, with leaves referencing ranges
+/// of a primitive array that is sorted to match. Compared to [BvhNode] there is no pointer chasing
+/// or dynamic dispatch between nodes, only when intersecting the primitives in a leaf.
+pub struct FlatBvh {
+    nodes: Vec<FlatBvhNode>,
+    primitives: Vec<Arc<dyn Hittable>>,
+}
+
+impl FlatBvh {
+    /// Builds a flattened BVH over `src_objects`, also returning statistics about the shape of the tree
+    pub fn build(src_objects: &[Arc<dyn Hittable>], time_0: f32, time_1: f32, builder: BvhBuilder) -> (FlatBvh, BvhStats) {
+        let mut primitives = src_objects.to_vec();
+        let mut nodes = Vec::with_capacity(2 * primitives.len());
+        let mut stats = BvhStats {
+            num_primitives: primitives.len(),
+            ..BvhStats::default()
+        };
+        if !primitives.is_empty() {
+            FlatBvh::build_node(&mut nodes, &mut primitives, 0, time_0, time_1, builder, &mut seeded_rng(MEDIAN_SEED), 1, &mut stats);
+            let root_area = nodes[0].bounding_box.surface_area();
+            if root_area > 0.0 {
+                stats.sah_cost /= root_area;
+            }
+        }
+
+        (FlatBvh { nodes, primitives }, stats)
+    }
+
+    /// Appends the subtree over `primitives` (which start at `first_primitive` in the final
+    /// primitive array) to `nodes`, returning the index of its root
+    #[allow(clippy::too_many_arguments)]
+    fn build_node(nodes: &mut Vec<FlatBvhNode>, primitives: &mut [Arc<dyn Hittable>], first_primitive: usize, time_0: f32, time_1: f32, builder: BvhBuilder, rng: &mut Rng, depth: usize, stats: &mut BvhStats) -> usize {
+        let bounding_box = primitives.iter()
+            .map(|primitive| BvhNode::object_box(primitive, time_0, time_1))
+            .reduce(|a, b| Aabb::surrounding_box(&a, &b))
+            .unwrap();
+        let area = bounding_box.surface_area();
+        let node_index = nodes.len();
+        nodes.push(FlatBvhNode {
+            bounding_box,
+            offset: first_primitive as u32,
+            num_primitives: primitives.len() as u32,
+            split_axis: 0,
+            first_is_lower: true,
+        });
+        stats.max_depth = stats.max_depth.max(depth);
+
+        let num_primitives = primitives.len();
+        if num_primitives == 1 || depth >= MAX_DEPTH {
+            stats.num_leaves += 1;
+            stats.sah_cost += SAH_INTERSECTION_COST * num_primitives as f32 * area;
+            return node_index;
+        }
+
+        let (mid, split_cost) = BvhNode::partition(primitives, time_0, time_1, builder, rng);
+        let leaf_cost = SAH_INTERSECTION_COST * num_primitives as f32;
+        let make_leaf = num_primitives <= MAX_LEAF_PRIMITIVES && split_cost
+            .is_none_or(|cost| leaf_cost <= SAH_TRAVERSAL_COST + SAH_INTERSECTION_COST * cost / area);
+        if make_leaf {
+            stats.num_leaves += 1;
+            stats.sah_cost += leaf_cost * area;
+            return node_index;
+        }
+
+        stats.num_interior_nodes += 1;
+        stats.sah_cost += SAH_TRAVERSAL_COST * area;
+        let (left_primitives, right_primitives) = primitives.split_at_mut(mid);
+        let left_index = FlatBvh::build_node(nodes, left_primitives, first_primitive, time_0, time_1, builder, rng, depth + 1, stats);
+        let right_index = FlatBvh::build_node(nodes, right_primitives, first_primitive + mid, time_0, time_1, builder, rng, depth + 1, stats);
+
+        let centroid_offset = nodes[right_index].bounding_box.centroid() - nodes[left_index].bounding_box.centroid();
+        let axis_offset = centroid_offset.abs();
+        let split_axis = if axis_offset.x >= axis_offset.y && axis_offset.x >= axis_offset.z {
+            0
+        } else if axis_offset.y >= axis_offset.z {
+            1
+        } else {
+            2
+        };
+        nodes[node_index] = FlatBvhNode {
+            bounding_box,
+            offset: right_index as u32,
+            num_primitives: 0,
+            split_axis,
+            first_is_lower: centroid_offset[split_axis as usize] >= 0.0,
+        };
+        node_index
+    }
+}
+
+impl Hittable for FlatBvh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult<'_>> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let inverse_direction = Vec3f::ONE / ray.direction;
+        let mut closest_hit = t_max;
+        let mut closest_hit_object: Option<HitResult> = None;
+
+        let mut stack = [0u32; MAX_DEPTH];
+        let mut stack_size = 0;
+        let mut node_index = 0usize;
+        loop {
+            let node = &self.nodes[node_index];
+            if node.bounding_box.does_hit_with_inverse_direction(ray, &inverse_direction, t_min, closest_hit) {
+                if node.num_primitives > 0 {
+                    let first = node.offset as usize;
+                    for primitive in &self.primitives[first..first + node.num_primitives as usize] {
+                        if let Some(hit) = primitive.hit(ray, t_min, closest_hit) {
+                            closest_hit = hit.t;
+                            closest_hit_object = Some(hit);
+                        }
+                    }
+                } else {
+                    // visit the child nearest the ray origin first so hits found there can cull the other child
+                    let is_descending = inverse_direction[node.split_axis as usize] < 0.0;
+                    let (near, far) = if is_descending == node.first_is_lower {
+                        (node.offset, node_index as u32 + 1)
+                    } else {
+                        (node_index as u32 + 1, node.offset)
+                    };
+                    stack[stack_size] = far;
+                    stack_size += 1;
+                    node_index = near as usize;
+                    continue;
+                }
+            }
+
+            if stack_size == 0 {
+                break;
+            }
+            stack_size -= 1;
+            node_index = stack[stack_size] as usize;
+        }
+
+        closest_hit_object
+    }
+
+    fn bounding_box(&self, _time_0: f32, _time_1: f32) -> Option<Aabb> {
+        self.nodes.first().map(|root| root.bounding_box)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use super::*;
+    use crate::core::color::ColorRgbF;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::lambertian::LambertianMaterial;
+
+    #[test]
+    fn coincident_primitives_stay_within_the_traversal_stack() {
+        let material = Arc::new(LambertianMaterial::new_with_solid_color(&ColorRgbF::ONE));
+        let objects: Vec<Arc<dyn Hittable>> = (0..100_000)
+            .map(|_| Arc::new(Sphere { centre: Vec3f::ZERO, radius: 1.0, material: material.clone() }) as Arc<dyn Hittable>)
+            .collect();
+        for builder in [BvhBuilder::Median, BvhBuilder::Sah] {
+            let (bvh, stats) = FlatBvh::build(&objects, 0.0, 1.0, builder);
+            assert!(stats.max_depth <= MAX_DEPTH);
+            let ray = Ray { origin: Vec3f::new(0.0, 0.0, 5.0), direction: Vec3f::new(0.0, 0.0, -1.0), time: 0.0 };
+            let hit = bvh.hit(&ray, 0.001, f32::MAX).expect("ray towards the spheres should hit them");
+            assert!((hit.t - 4.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn nodes_at_the_maximum_depth_become_leaves() {
+        let material = Arc::new(LambertianMaterial::new_with_solid_color(&ColorRgbF::ONE));
+        let mut primitives: Vec<Arc<dyn Hittable>> = (0..16)
+            .map(|i| Arc::new(Sphere { centre: Vec3f::new(i as f32 * 3.0, 0.0, 0.0), radius: 1.0, material: material.clone() }) as Arc<dyn Hittable>)
+            .collect();
+        let mut nodes = Vec::new();
+        let mut stats = BvhStats::default();
+        FlatBvh::build_node(&mut nodes, &mut primitives, 0, 0.0, 1.0, BvhBuilder::Sah, &mut seeded_rng(MEDIAN_SEED), MAX_DEPTH, &mut stats);
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].num_primitives, 16);
+    }
+
+    /// Counts how many times the contained hittable is tested against a ray
+    struct CountingHittable {
+        hittable: Sphere,
+        hits: Arc<AtomicUsize>,
+    }
+
+    impl Hittable for CountingHittable {
+        fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult<'_>> {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            self.hittable.hit(ray, t_min, t_max)
+        }
+
+        fn bounding_box(&self, time_0: f32, time_1: f32) -> Option<Aabb> {
+            self.hittable.bounding_box(time_0, time_1)
+        }
+    }
+
+    #[test]
+    fn nearer_children_are_visited_first_in_both_directions() {
+        let material = Arc::new(LambertianMaterial::new_with_solid_color(&ColorRgbF::ONE));
+        let hits = Arc::new(AtomicUsize::new(0));
+        // the spheres are spread further along y than x, but are ascending along x and
+        // descending along y, so children split along either axis have opposite orders along the other
+        let step = Vec3f::new(1.0, -2.0, 0.0);
+        let objects: Vec<Arc<dyn Hittable>> = (0..64)
+            .map(|i| Arc::new(CountingHittable {
+                hittable: Sphere { centre: i as f32 * step, radius: 0.5, material: material.clone() },
+                hits: hits.clone(),
+            }) as Arc<dyn Hittable>)
+            .collect();
+        for builder in [BvhBuilder::Median, BvhBuilder::Sah] {
+            let (bvh, _) = FlatBvh::build(&objects, 0.0, 1.0, builder);
+            for (origin, direction, expected_t) in [(-2.0 * step, step, 2.0 - 0.5 / step.length()), (65.0 * step, -step, 2.0 - 0.5 / step.length())] {
+                hits.store(0, Ordering::Relaxed);
+                let ray = Ray { origin, direction, time: 0.0 };
+                let hit = bvh.hit(&ray, 0.001, f32::MAX).expect("ray along the spheres should hit them");
+                assert!((hit.t - expected_t).abs() < 1e-4);
+                let num_hits = hits.load(Ordering::Relaxed);
+                assert!(num_hits <= 2 * MAX_LEAF_PRIMITIVES, "{:?} tested {} spheres for a ray along {}", builder, num_hits, direction);
+            }
+        }
+    }
+}
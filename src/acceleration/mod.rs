@@ -1,2 +1,3 @@
 pub mod aabb;
-pub mod bvh;
\ No newline at end of file
+pub mod bvh;
+pub mod flat_bvh;
@@ -0,0 +1,30 @@
+use serde::Deserialize;
+
+/// Spends more samples on noisy pixels than on converged ones. Every pixel gets `min_samples`
+/// samples, then pixels whose estimated error is above `noise_threshold` keep doubling their
+/// samples until they drop below it or reach the render's sample count.
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub struct AdaptiveSampling {
+    /// Samples taken before trusting the error estimate. Too few and pixels whose rare bright
+    /// paths haven't been found yet look converged, slightly darkening the image.
+    pub min_samples: usize,
+    /// Largest acceptable standard error of a pixel, as a fraction of the display's range
+    pub noise_threshold: f32,
+}
+
+impl AdaptiveSampling {
+    /// Number of samples to add to a pixel that has `sample_count` samples with an estimated error
+    /// of `estimated_error`, never taking it past `max_samples`
+    pub fn samples_wanted(&self, sample_count: usize, estimated_error: f32, max_samples: usize) -> usize {
+        // the error can't be estimated from fewer than two samples
+        let min_samples = self.min_samples.max(2);
+        let target = if sample_count < min_samples {
+            min_samples
+        } else if estimated_error > self.noise_threshold {
+            2 * sample_count
+        } else {
+            sample_count
+        };
+        target.min(max_samples).saturating_sub(sample_count)
+    }
+}
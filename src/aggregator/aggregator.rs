@@ -1,59 +1,259 @@
-use std::fmt::Write;
-use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use log::info;
 
-use crate::core::color::ColorRgbF;
-use crate::core::ray::Ray;
-use crate::core::rehnda_math::random_in_range;
+use crate::aggregator::adaptive_sampling::AdaptiveSampling;
+use crate::aggregator::crop_window::CropWindow;
+use crate::aggregator::integrator::Integrator;
+use crate::aggregator::path_termination::{PathSettings, PathTermination};
+use crate::aggregator::progressive::ProgressiveRendering;
+use crate::aggregator::render_control::{CancellationToken, RenderProgress};
+use crate::aggregator::tile_scheduler::{Tile, TileQueue};
+use crate::image::filter::ReconstructionFilter;
 use crate::image::image_buffer::ImageBuffer;
+use crate::sampler::Sampler;
 use crate::scene::Scene;
 
 #[derive(Debug, Copy, Clone)]
 pub struct AggregationConfig {
     pub samples_per_pixel: usize,
-    pub max_sample_depth: usize,
-}
-
-fn sample_ray(ray: &Ray, scene: &Scene, depth: usize) -> ColorRgbF {
-    if depth == 0 {
-        return ColorRgbF::ZERO;
-    }
-
-    return if let Some(hit_result) = scene.world.hit(ray, 0.001, f32::MAX) {
-        let emitted = hit_result.material.emitted(&hit_result.uv, &hit_result.hit_location);
-        if let Some(scatter) = hit_result.material.scatter(ray, &hit_result) {
-            emitted + scatter.attenuation * sample_ray(&scatter.scattered_ray, scene, depth - 1)
-        } else {
-            // no scatter, so only return the emitted light
-            emitted
-        }
-    } else {
-        // hit nothing in the world so return a background color
-        scene.background
-    }
-}
-
-pub fn sample_pixels(aggregation_config: &AggregationConfig, scene: &Scene, out_image_buffer: &mut ImageBuffer, print_progress: bool) {
-    let progress_bar: Option<ProgressBar> = if print_progress {
-        let pb = ProgressBar::new(100u64);
-        pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-            .unwrap()
-            .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
-            .progress_chars("#>-"));
-        Some(pb)
-    } else {
-        None
-    };
-    for j in (0..out_image_buffer.image_height).rev() {
-        if let Some(pb) = progress_bar.as_ref() {
-            pb.set_position((((out_image_buffer.image_height - j) as f32 / out_image_buffer.image_height as f32) * 100f32) as u64);
-        }
-        for i in 0..out_image_buffer.image_width {
-            for _s in 0..aggregation_config.samples_per_pixel {
-                let u = (i as f32 + random_in_range(0.0, 1.0)) / (out_image_buffer.image_width - 1) as f32;
-                let v = (j as f32 + random_in_range(0.0, 1.0)) / (out_image_buffer.image_height - 1) as f32;
-                let ray = scene.camera.get_ray(u, v);
-                out_image_buffer.write_color_sample(i, j, sample_ray(&ray, scene, aggregation_config.max_sample_depth));
+    pub path_settings: PathSettings,
+    pub integrator: Integrator,
+    pub sampler: Sampler,
+    pub filter: ReconstructionFilter,
+    /// Only the pixels within the window are sampled, when set
+    pub crop_window: Option<CropWindow>,
+    /// When set, `samples_per_pixel` is only the most samples a pixel can receive
+    pub adaptive_sampling: Option<AdaptiveSampling>,
+    pub progressive: Option<ProgressiveRendering>,
+    /// Seeds the generator of every sample, renders with the same seed and settings are identical
+    pub seed: u64,
+}
+
+/// Number of threads the machine can run in parallel
+pub fn available_threads() -> usize {
+    thread::available_parallelism().map(NonZeroUsize::get).unwrap_or(1)
+}
+
+/// Renders the image, or the part of it within the crop window, with `num_threads` threads pulling
+/// tiles from a shared queue. Without adaptive sampling every pixel receives exactly
+/// `samples_per_pixel` samples in a single pass, with it the image is rendered in passes that each
+/// add samples to the pixels that still need them, until no pixel does. Progressive rendering
+/// additionally caps the samples of each pass, handing the image so far to `on_checkpoint`
+/// whenever a checkpoint is due. Timed checkpoints are checked between tiles, so a pass that runs
+/// past one is interrupted to write it and then carried on.
+///
+/// Rendering carries on from the samples already in `framebuffer`, which is empty for a new render.
+/// `on_progress` is called from the render threads as tiles complete. Cancelling makes the threads
+/// finish their current tile and return the image as it is.
+pub fn render_image(aggregation_config: &AggregationConfig, scene: &Scene, framebuffer: ImageBuffer, num_threads: usize,
+                    cancellation: &CancellationToken, on_progress: impl Fn(&RenderProgress) + Sync, mut on_checkpoint: impl FnMut(&ImageBuffer)) -> ImageBuffer {
+    let (image_width, image_height) = (framebuffer.image_width, framebuffer.image_height);
+    let region = aggregation_config.crop_window.map_or(
+        Tile { x_start: 0, y_start: 0, x_end: image_width, y_end: image_height },
+        |crop_window| crop_window.pixel_bounds(image_width, image_height),
+    );
+    let tile_merger = Mutex::new(TileMerger {
+        next_index: 0,
+        tiles: BTreeMap::new(),
+        progress: RenderProgress {
+            pass: 0,
+            finished_pixels: region.width() * region.height() - aggregation_config.unfinished_pixels(&framebuffer, &region),
+            total_pixels: region.width() * region.height(),
+            samples_taken: framebuffer.total_sample_count(),
+        },
+    });
+    let framebuffer = Mutex::new(framebuffer);
+
+    let checkpoint_interval = aggregation_config.progressive
+        .and_then(|progressive| progressive.checkpoint_seconds)
+        .map(Duration::from_secs_f32);
+    let mut last_checkpoint = (0, Instant::now());
+    let mut pass = 0;
+    let mut tile_queue = TileQueue::new(&region);
+    let pass_samples = AtomicUsize::new(0);
+    loop {
+        let checkpoint_deadline = checkpoint_interval.map(|interval| last_checkpoint.1 + interval);
+        let sample_limit = aggregation_config.progressive.map_or(usize::MAX, |_| ProgressiveRendering::pass_sample_limit(pass));
+
+        let checkpoint_interrupted = AtomicBool::new(false);
+        thread::scope(|scope| {
+            for _ in 0..num_threads {
+                scope.spawn(|| {
+                    // cancelling and checkpoints are only checked between tiles, so every tile
+                    // taken gets merged
+                    while !cancellation.is_cancelled() && !checkpoint_interrupted.load(Ordering::Relaxed) {
+                        let Some((tile_index, tile)) = tile_queue.next_tile() else {
+                            break;
+                        };
+                        // the samples taken within a tile's pixels are only added by the thread
+                        // rendering it, so what it reads here can't change until it merges them
+                        let (sample_ranges, unfinished_pixels) = {
+                            let framebuffer = framebuffer.lock().unwrap();
+                            (aggregation_config.tile_sample_ranges(&framebuffer, &tile, sample_limit), aggregation_config.unfinished_pixels(&framebuffer, &tile))
+                        };
+                        let tile_samples = sample_tile(aggregation_config, scene, &tile, &sample_ranges, image_width, image_height);
+                        pass_samples.fetch_add(tile_samples.buffer.total_sample_count(), Ordering::Relaxed);
+
+                        // samples splatted across tile borders are added in tile order, so the
+                        // sums are the same whichever thread finishes first
+                        let mut tile_merger = tile_merger.lock().unwrap();
+                        tile_merger.tiles.insert(tile_index, (tile, tile_samples, unfinished_pixels));
+                        let mut framebuffer = framebuffer.lock().unwrap();
+                        while let Some((tile, tile_samples, unfinished_pixels)) = tile_merger.next_in_order() {
+                            framebuffer.add_buffer_at(tile_samples.x_start, tile_samples.y_start, &tile_samples.buffer);
+                            tile_merger.progress.finished_pixels += unfinished_pixels - aggregation_config.unfinished_pixels(&framebuffer, &tile);
+                            tile_merger.progress.samples_taken += tile_samples.buffer.total_sample_count();
+                            on_progress(&tile_merger.progress);
+                        }
+                        drop(framebuffer);
+                        drop(tile_merger);
+
+                        // checked after rendering a tile so every interrupted pass makes progress
+                        if checkpoint_deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                            checkpoint_interrupted.store(true, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        if cancellation.is_cancelled() {
+            break;
+        }
+        if checkpoint_interrupted.into_inner() && !tile_queue.is_empty() {
+            // every tile taken was rendered and merged, so the pass carries on from the first tile
+            // nobody took, with the same tile queue and merge order
+            on_checkpoint(&framebuffer.lock().unwrap());
+            last_checkpoint = (pass, Instant::now());
+            continue;
+        }
+        if pass_samples.swap(0, Ordering::Relaxed) == 0 {
+            break;
+        }
+
+        if let Some(progressive) = &aggregation_config.progressive {
+            let (checkpoint_pass, checkpoint_time) = last_checkpoint;
+            if progressive.checkpoint_due(pass + 1 - checkpoint_pass, checkpoint_time.elapsed()) {
+                on_checkpoint(&framebuffer.lock().unwrap());
+                last_checkpoint = (pass + 1, Instant::now());
+            }
+        }
+        pass += 1;
+        tile_queue = TileQueue::new(&region);
+        tile_merger.lock().unwrap().start_pass(pass);
+    }
+
+    let framebuffer = framebuffer.into_inner().unwrap();
+    info!("Took {:.1} samples per pixel on average", framebuffer.total_sample_count() as f32 / (region.width() * region.height()) as f32);
+    framebuffer
+}
+
+impl AggregationConfig {
+    /// Renders `samples_per_pixel` samples for every pixel with the default integrator, sampler,
+    /// filter and path termination
+    pub fn new(samples_per_pixel: usize) -> AggregationConfig {
+        AggregationConfig {
+            samples_per_pixel,
+            path_settings: PathSettings {
+                max_depth: None,
+                termination: PathTermination::default(),
+            },
+            integrator: Integrator::default(),
+            sampler: Sampler::default(),
+            filter: ReconstructionFilter::default(),
+            crop_window: None,
+            adaptive_sampling: None,
+            progressive: None,
+            seed: 0,
+        }
+    }
+
+    /// Indices of the samples to take at each pixel of `tile`, row by row, without taking any
+    /// pixel past `sample_limit` samples
+    fn tile_sample_ranges(&self, framebuffer: &ImageBuffer, tile: &Tile, sample_limit: usize) -> Vec<Range<usize>> {
+        let mut sample_ranges = Vec::with_capacity(tile.width() * tile.height());
+        for j in tile.y_start..tile.y_end {
+            for i in tile.x_start..tile.x_end {
+                let sample_count = framebuffer.sample_count(i, j);
+                let samples_wanted = self.samples_wanted(framebuffer, i, j).min(sample_limit.saturating_sub(sample_count));
+                sample_ranges.push(sample_count..sample_count + samples_wanted);
             }
         }
+        sample_ranges
+    }
+
+    /// Number of pixels in `tile` that still want samples
+    fn unfinished_pixels(&self, framebuffer: &ImageBuffer, tile: &Tile) -> usize {
+        (tile.y_start..tile.y_end)
+            .flat_map(|j| (tile.x_start..tile.x_end).map(move |i| (i, j)))
+            .filter(|&(i, j)| self.samples_wanted(framebuffer, i, j) > 0)
+            .count()
+    }
+
+    fn samples_wanted(&self, framebuffer: &ImageBuffer, x: usize, y: usize) -> usize {
+        let sample_count = framebuffer.sample_count(x, y);
+        match &self.adaptive_sampling {
+            Some(adaptive_sampling) => adaptive_sampling.samples_wanted(sample_count, framebuffer.estimated_error(x, y), self.samples_per_pixel),
+            None => self.samples_per_pixel.saturating_sub(sample_count),
+        }
+    }
+}
+
+/// Keeps track of the tiles of a pass that finished rendering before some tile handed out ahead of
+/// them, so tiles are merged into the image in order, and of the progress made by merged tiles
+struct TileMerger {
+    next_index: usize,
+    tiles: BTreeMap<usize, (Tile, TileSamples, usize)>,
+    progress: RenderProgress,
+}
+
+impl TileMerger {
+    fn start_pass(&mut self, pass: usize) {
+        self.next_index = 0;
+        self.progress.pass = pass;
     }
-}
\ No newline at end of file
+
+    fn next_in_order(&mut self) -> Option<(Tile, TileSamples, usize)> {
+        let next = self.tiles.remove(&self.next_index)?;
+        self.next_index += 1;
+        Some(next)
+    }
+}
+
+/// Samples of a tile, in a buffer covering the tile and the pixels around it its samples are
+/// splatted into
+struct TileSamples {
+    x_start: usize,
+    y_start: usize,
+    buffer: ImageBuffer,
+}
+
+/// Takes the samples in `sample_ranges` for the pixels of `tile`
+fn sample_tile(aggregation_config: &AggregationConfig, scene: &Scene, tile: &Tile, sample_ranges: &[Range<usize>], image_width: usize, image_height: usize) -> TileSamples {
+    let filter = &aggregation_config.filter;
+    let pixel_radius = filter.pixel_radius();
+    let (x_start, y_start) = (tile.x_start.saturating_sub(pixel_radius), tile.y_start.saturating_sub(pixel_radius));
+    let (x_end, y_end) = ((tile.x_end + pixel_radius).min(image_width), (tile.y_end + pixel_radius).min(image_height));
+    let mut buffer = ImageBuffer::new(x_end - x_start, y_end - y_start);
+    let mut sample_ranges = sample_ranges.iter();
+    for j in tile.y_start..tile.y_end {
+        for i in tile.x_start..tile.x_end {
+            let pixel_index = (i + j * image_width) as u64;
+            for s in sample_ranges.next().unwrap().clone() {
+                let mut sampler = aggregation_config.sampler.pixel_sampler(aggregation_config.seed, pixel_index, s, aggregation_config.samples_per_pixel);
+                let pixel_offset = sampler.get_2d();
+                let u = (i as f32 + pixel_offset.x) / (image_width - 1) as f32;
+                let v = (j as f32 + pixel_offset.y) / (image_height - 1) as f32;
+                let ray = scene.camera.get_ray(u, v, &mut sampler);
+                let color = aggregation_config.integrator.sample_ray(&ray, scene, &aggregation_config.path_settings, &mut sampler);
+                buffer.splat_color_sample(i - x_start, j - y_start, pixel_offset, color, filter);
+            }
+        }
+    }
+    TileSamples { x_start, y_start, buffer }
+}
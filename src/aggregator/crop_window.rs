@@ -0,0 +1,37 @@
+use serde::Deserialize;
+use crate::aggregator::tile_scheduler::Tile;
+
+/// Part of the image to render, measured from the top left corner of the image as it's viewed.
+/// The rest of the image is left without samples, so the render can be merged with other renders
+/// of the same settings to refine just that region.
+#[derive(Debug, Copy, Clone, Deserialize, PartialEq)]
+pub enum CropWindow {
+    /// Pixels from the start up to but excluding the end, at the rendered resolution
+    Pixels { x_start: usize, y_start: usize, x_end: usize, y_end: usize },
+    /// Fractions of the image's width and height, in the unit square
+    Normalised { x_min: f32, y_min: f32, x_max: f32, y_max: f32 },
+}
+
+impl CropWindow {
+    /// Pixels covered by the window, with rows counted from the bottom of the image like the
+    /// image buffer, and clipped to the image
+    pub fn pixel_bounds(&self, image_width: usize, image_height: usize) -> Tile {
+        let (x_start, y_start, x_end, y_end) = match *self {
+            CropWindow::Pixels { x_start, y_start, x_end, y_end } => (x_start, y_start, x_end, y_end),
+            CropWindow::Normalised { x_min, y_min, x_max, y_max } => (
+                (x_min.clamp(0.0, 1.0) * image_width as f32).floor() as usize,
+                (y_min.clamp(0.0, 1.0) * image_height as f32).floor() as usize,
+                (x_max.clamp(0.0, 1.0) * image_width as f32).ceil() as usize,
+                (y_max.clamp(0.0, 1.0) * image_height as f32).ceil() as usize,
+            ),
+        };
+        let (x_end, y_end) = (x_end.min(image_width), y_end.min(image_height));
+        let (x_start, y_start) = (x_start.min(x_end), y_start.min(y_end));
+        Tile {
+            x_start,
+            y_start: image_height - y_end,
+            x_end,
+            y_end: image_height - y_start,
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use serde::Deserialize;
+use crate::aggregator::path_termination::{PathSettings, PathState};
+use crate::core::color::ColorRgbF;
+use crate::core::ray::Ray;
+use crate::hittable::HitResult;
+use crate::light::PunctualLight;
+use crate::sampler::PixelSampler;
+use crate::scene::Scene;
+
+#[derive(Debug, Copy, Clone, Deserialize, Default, PartialEq, Eq)]
+pub enum Integrator {
+    /// Only finds lights when a scattered ray happens to hit one
+    PathTracer,
+    /// Next event estimation, also aims a shadow ray at a random light at every non-specular bounce
+    NextEventEstimation,
+    /// Combines the light samples of next event estimation with lights found by scattered rays,
+    /// weighting each with the power heuristic so whichever technique suits the light and the
+    /// material dominates, e.g. large lights reflected in glossy metals
+    #[default]
+    MultipleImportanceSampling,
+}
+
+impl Integrator {
+    /// Estimates the radiance arriving back along the camera ray `ray`
+    pub fn sample_ray(&self, ray: &Ray, scene: &Scene, path_settings: &PathSettings, sampler: &mut PixelSampler) -> ColorRgbF {
+        let path = PathState::CAMERA_RAY;
+        match self {
+            Integrator::PathTracer => sample_ray_path_traced(ray, scene, path_settings, &path, sampler),
+            Integrator::NextEventEstimation => sample_ray_next_event_estimation(ray, scene, path_settings, &path, true, sampler),
+            Integrator::MultipleImportanceSampling => sample_ray_multiple_importance_sampled(ray, scene, path_settings, &path, None, sampler),
+        }
+    }
+}
+
+fn sample_ray_path_traced(ray: &Ray, scene: &Scene, path_settings: &PathSettings, path: &PathState, sampler: &mut PixelSampler) -> ColorRgbF {
+    if let Some(hit_result) = scene.world.hit(ray, 0.001, f32::MAX) {
+        sampler.start_bounce(path.bounces);
+        let emitted = hit_result.material.emitted(&hit_result.uv, &hit_result.hit_location);
+        if let Some(scatter) = hit_result.material.scatter(ray, &hit_result, sampler) {
+            emitted + match path.scatter(&scatter.attenuation, path_settings, sampler) {
+                Some((next_path, weight)) => weight * scatter.attenuation * sample_ray_path_traced(&scatter.scattered_ray, scene, path_settings, &next_path, sampler),
+                None => ColorRgbF::ZERO,
+            }
+        } else {
+            // no scatter, so only return the emitted light
+            emitted
+        }
+    } else {
+        // hit nothing in the world so return a background color
+        scene.background.radiance(&ray.direction)
+    }
+}
+
+/// Light reached by a scattered ray after a bounce that sampled the lights directly has already
+/// been counted, so `include_emitted` is false for those rays to avoid counting it twice
+fn sample_ray_next_event_estimation(ray: &Ray, scene: &Scene, path_settings: &PathSettings, path: &PathState, include_emitted: bool, sampler: &mut PixelSampler) -> ColorRgbF {
+    if let Some(hit_result) = scene.world.hit(ray, 0.001, f32::MAX) {
+        sampler.start_bounce(path.bounces);
+        let emitted = if include_emitted {
+            hit_result.material.emitted(&hit_result.uv, &hit_result.hit_location)
+        } else {
+            ColorRgbF::ZERO
+        };
+        // specular bounces can't sample the lights, so they have to be found by the scattered ray
+        let is_specular = hit_result.material.is_specular();
+        let direct_light = if is_specular {
+            ColorRgbF::ZERO
+        } else {
+            sample_direct_light(ray, &hit_result, scene, false, sampler)
+        };
+        let Some(scatter) = hit_result.material.scatter(ray, &hit_result, sampler) else {
+            return emitted + direct_light;
+        };
+
+        emitted + direct_light + match path.scatter(&scatter.attenuation, path_settings, sampler) {
+            Some((next_path, weight)) => weight * scatter.attenuation
+                * sample_ray_next_event_estimation(&scatter.scattered_ray, scene, path_settings, &next_path, is_specular, sampler),
+            None => ColorRgbF::ZERO,
+        }
+    } else if include_emitted || scene.background.environment().is_none() {
+        scene.background.radiance(&ray.direction)
+    } else {
+        // the environment is one of the lights, so was sampled directly at the previous bounce
+        ColorRgbF::ZERO
+    }
+}
+
+/// `scatter_pdf` is the density the previous bounce chose `ray` with, or `None` if the previous
+/// bounce was specular (or there wasn't one) so lights hit by `ray` couldn't have been sampled directly
+fn sample_ray_multiple_importance_sampled(ray: &Ray, scene: &Scene, path_settings: &PathSettings, path: &PathState, scatter_pdf: Option<f32>, sampler: &mut PixelSampler) -> ColorRgbF {
+    let Some(hit_result) = scene.world.hit(ray, 0.001, f32::MAX) else {
+        let background = scene.background.radiance(&ray.direction);
+        return match (scatter_pdf, scene.background.environment()) {
+            (Some(scatter_pdf), Some(_)) => power_heuristic(scatter_pdf, lights_pdf(scene, ray)) * background,
+            _ => background,
+        };
+    };
+    sampler.start_bounce(path.bounces);
+    let mut emitted = hit_result.material.emitted(&hit_result.uv, &hit_result.hit_location);
+    if let Some(scatter_pdf) = scatter_pdf {
+        if emitted != ColorRgbF::ZERO {
+            emitted *= power_heuristic(scatter_pdf, lights_pdf(scene, ray));
+        }
+    }
+
+    let is_specular = hit_result.material.is_specular();
+    let direct_light = if is_specular {
+        ColorRgbF::ZERO
+    } else {
+        sample_direct_light(ray, &hit_result, scene, true, sampler)
+    };
+    let Some(scatter) = hit_result.material.scatter(ray, &hit_result, sampler) else {
+        return emitted + direct_light;
+    };
+
+    let next_scatter_pdf = if is_specular { None } else { Some(scatter.pdf) };
+    emitted + direct_light + match path.scatter(&scatter.attenuation, path_settings, sampler) {
+        Some((next_path, weight)) => weight * scatter.attenuation
+            * sample_ray_multiple_importance_sampled(&scatter.scattered_ray, scene, path_settings, &next_path, next_scatter_pdf, sampler),
+        None => ColorRgbF::ZERO,
+    }
+}
+
+/// Power heuristic (with an exponent of two) weight for a sample taken with density `pdf` when
+/// the other technique would have chosen it with density `other_pdf`
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let pdf_squared = pdf * pdf;
+    pdf_squared / (pdf_squared + other_pdf * other_pdf)
+}
+
+/// Number of lights sampled directly: the emissive objects, the punctual lights and the
+/// environment if there is one
+fn num_lights(scene: &Scene) -> usize {
+    scene.lights.len() + scene.punctual_lights.len() + scene.background.environment().is_some() as usize
+}
+
+/// Density of sampling the direction of `ray` by picking a random light and then a direction towards it
+fn lights_pdf(scene: &Scene, ray: &Ray) -> f32 {
+    let num_lights = num_lights(scene);
+    if num_lights == 0 {
+        return 0.0;
+    }
+    // lights can overlap as seen from the ray origin, so the direction could have been chosen via any of them
+    let objects_pdf = scene.lights.iter().map(|light| light.pdf_value(ray)).sum::<f32>();
+    let environment_pdf = scene.background.environment().map_or(0.0, |environment| environment.pdf(&ray.direction));
+    (objects_pdf + environment_pdf) / num_lights as f32
+}
+
+/// Light arriving directly from the scene's lights at `hit_result`, estimated with a shadow ray
+/// towards a random point on a random light, a punctual light or a direction towards the
+/// environment. With `weighted` the estimate is weighted against the material's scatter finding
+/// the same light.
+fn sample_direct_light(ray_in: &Ray, hit_result: &HitResult, scene: &Scene, weighted: bool, sampler: &mut PixelSampler) -> ColorRgbF {
+    let num_lights = num_lights(scene);
+    if num_lights == 0 {
+        return ColorRgbF::ZERO;
+    }
+
+    // punctual lights come after the emissive objects and before the environment
+    let light_index = sampler.get_index(num_lights);
+    if let Some(light) = light_index.checked_sub(scene.lights.len()).and_then(|index| scene.punctual_lights.get(index)) {
+        return num_lights as f32 * sample_punctual_light(ray_in, hit_result, light.as_ref(), scene);
+    }
+    let direction = match scene.lights.get(light_index) {
+        Some(light) => light.random_direction(&hit_result.hit_location, ray_in.time, sampler),
+        None => scene.background.environment().unwrap().sample_direction(sampler.get_2d()),
+    };
+    let shadow_ray = Ray {
+        origin: hit_result.hit_location,
+        direction,
+        time: ray_in.time,
+    };
+    let bsdf = hit_result.material.eval(ray_in, hit_result, &shadow_ray.direction);
+    let light_pdf = lights_pdf(scene, &shadow_ray);
+    if light_pdf <= 0.0 || bsdf == ColorRgbF::ZERO {
+        return ColorRgbF::ZERO;
+    }
+
+    // whichever light was picked, the shadow ray sees whatever is in its direction, which the
+    // density of every light choosing the direction accounts for
+    let emitted = match scene.world.hit(&shadow_ray, 0.001, f32::MAX) {
+        Some(light_hit) => light_hit.material.emitted(&light_hit.uv, &light_hit.hit_location),
+        None => scene.background.environment().map_or(ColorRgbF::ZERO, |environment| environment.radiance(&shadow_ray.direction)),
+    };
+    let weight = if weighted {
+        power_heuristic(light_pdf, hit_result.material.pdf(ray_in, hit_result, &shadow_ray.direction))
+    } else {
+        1.0
+    };
+    weight * bsdf * emitted / light_pdf
+}
+
+/// Light arriving at `hit_result` from `light` if nothing is in the way. Scattered rays can never
+/// hit a punctual light, so there's nothing to weight the estimate against.
+fn sample_punctual_light(ray_in: &Ray, hit_result: &HitResult, light: &dyn PunctualLight, scene: &Scene) -> ColorRgbF {
+    let Some(incident) = light.sample_incident(&hit_result.hit_location) else {
+        return ColorRgbF::ZERO;
+    };
+    let bsdf = hit_result.material.eval(ray_in, hit_result, &incident.direction);
+    if bsdf == ColorRgbF::ZERO {
+        return ColorRgbF::ZERO;
+    }
+
+    let shadow_ray = Ray {
+        origin: hit_result.hit_location,
+        direction: incident.direction,
+        time: ray_in.time,
+    };
+    if scene.world.hit(&shadow_ray, 0.001, incident.distance).is_some() {
+        return ColorRgbF::ZERO;
+    }
+    bsdf * incident.irradiance
+}
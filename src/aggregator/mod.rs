@@ -1,2 +1,10 @@
 mod aggregator;
-pub use aggregator::*;
\ No newline at end of file
+pub use aggregator::*;
+
+pub mod adaptive_sampling;
+pub mod crop_window;
+pub mod integrator;
+pub mod path_termination;
+pub mod progressive;
+pub mod render_control;
+pub mod tile_scheduler;
\ No newline at end of file
@@ -0,0 +1,70 @@
+use serde::Deserialize;
+use crate::core::color::ColorRgbF;
+use crate::sampler::PixelSampler;
+
+/// Highest probability of a path surviving Russian roulette, so paths that lose no energy (e.g.
+/// bouncing around inside glass) still end
+const MAX_SURVIVAL_PROBABILITY: f32 = 0.95;
+
+#[derive(Debug, Copy, Clone, Deserialize, PartialEq, Eq)]
+pub enum PathTermination {
+    /// Paths only end after `max_depth` rays, which then has to be set. Light that takes more
+    /// bounces to reach the camera is lost, darkening bright scenes.
+    MaxDepth,
+    /// After `min_depth` bounces paths are randomly terminated with a probability based on how
+    /// much their throughput has dropped, and surviving paths are weighted up to compensate. This
+    /// keeps the render unbiased while spending little time on paths carrying little light.
+    RussianRoulette { min_depth: usize },
+}
+
+impl Default for PathTermination {
+    fn default() -> Self {
+        PathTermination::RussianRoulette { min_depth: 3 }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct PathSettings {
+    /// Hard limit on the number of rays in a path, including the camera ray
+    pub max_depth: Option<usize>,
+    pub termination: PathTermination,
+}
+
+/// State carried along a path as it bounces through the scene
+#[derive(Debug, Copy, Clone)]
+pub struct PathState {
+    /// Number of scattered rays so far, zero for the camera ray
+    pub bounces: usize,
+    /// Product of the attenuations along the path, i.e. how much of the light found at the end of
+    /// the path would reach the camera
+    pub throughput: ColorRgbF,
+}
+
+impl PathState {
+    pub const CAMERA_RAY: PathState = PathState {
+        bounces: 0,
+        throughput: ColorRgbF::ONE,
+    };
+
+    /// State of the path after scattering with `attenuation`, along with the weight for the
+    /// scattered ray's radiance that compensates for the paths terminated by Russian roulette.
+    /// `None` if the path ends here.
+    pub fn scatter(&self, attenuation: &ColorRgbF, settings: &PathSettings, sampler: &mut PixelSampler) -> Option<(PathState, f32)> {
+        let bounces = self.bounces + 1;
+        if settings.max_depth.is_some_and(|max_depth| bounces >= max_depth) {
+            return None;
+        }
+
+        let throughput = self.throughput * *attenuation;
+        match settings.termination {
+            PathTermination::RussianRoulette { min_depth } if bounces >= min_depth => {
+                let survival_probability = throughput.max_element().min(MAX_SURVIVAL_PROBABILITY);
+                if sampler.get_1d() >= survival_probability {
+                    return None;
+                }
+                Some((PathState { bounces, throughput: throughput / survival_probability }, 1.0 / survival_probability))
+            }
+            _ => Some((PathState { bounces, throughput }, 1.0)),
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use std::time::Duration;
+use serde::Deserialize;
+
+/// Renders the image in passes that each double the samples a pixel can have, starting from one,
+/// so a rough version of the whole image is available early and keeps refining. The image
+/// rendered so far is handed back for writing out every `checkpoint_passes` passes and/or every
+/// `checkpoint_seconds` seconds.
+#[derive(Debug, Copy, Clone, Deserialize, Default)]
+pub struct ProgressiveRendering {
+    #[serde(default)]
+    pub checkpoint_passes: Option<usize>,
+    #[serde(default)]
+    pub checkpoint_seconds: Option<f32>,
+}
+
+impl ProgressiveRendering {
+    /// Most samples a pixel can have after pass `pass`, counting from zero
+    pub fn pass_sample_limit(pass: usize) -> usize {
+        u32::try_from(pass).ok().and_then(|pass| 1usize.checked_shl(pass)).unwrap_or(usize::MAX)
+    }
+
+    /// Whether to write out the image, given what has been rendered since it was last written
+    pub fn checkpoint_due(&self, passes_since_checkpoint: usize, time_since_checkpoint: Duration) -> bool {
+        self.checkpoint_passes.is_some_and(|passes| passes_since_checkpoint >= passes)
+            || self.checkpoint_seconds.is_some_and(|seconds| time_since_checkpoint.as_secs_f32() >= seconds)
+    }
+}
@@ -0,0 +1,38 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Stops a render from another thread, e.g. when the user cancels it. Clones share the same flag.
+/// A cancelled render finishes the tiles being rendered and returns the image as it is.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Cancels the render, returning whether it had already been cancelled
+    pub fn cancel(&self) -> bool {
+        self.cancelled.swap(true, Ordering::Relaxed)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// How far a render has got, reported each time a tile's samples are added to the image
+#[derive(Debug, Copy, Clone)]
+pub struct RenderProgress {
+    /// Pass being rendered, counting from zero. Renders without adaptive sampling or progressive
+    /// rendering only have one.
+    pub pass: usize,
+    /// Pixels that have all the samples they need
+    pub finished_pixels: usize,
+    /// Pixels being rendered, only those within the crop window if there is one
+    pub total_pixels: usize,
+    /// Samples taken so far, including any the render was resumed from
+    pub samples_taken: usize,
+}
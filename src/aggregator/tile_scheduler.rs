@@ -0,0 +1,66 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Width and height in pixels of the square tiles the image is split into, tiles along the right
+/// and top edges of the rendered region are cut short
+pub const TILE_SIZE: usize = 16;
+
+/// Rectangle of pixels rendered as one unit of work, from the start pixels up to but excluding the
+/// end pixels
+#[derive(Debug, Copy, Clone)]
+pub struct Tile {
+    pub x_start: usize,
+    pub y_start: usize,
+    pub x_end: usize,
+    pub y_end: usize,
+}
+
+impl Tile {
+    pub fn width(&self) -> usize {
+        self.x_end - self.x_start
+    }
+
+    pub fn height(&self) -> usize {
+        self.y_end - self.y_start
+    }
+}
+
+/// Tiles covering the image, handed out once each to whichever render thread asks next so threads
+/// that finish early pick up the remaining work
+pub struct TileQueue {
+    tiles: Vec<Tile>,
+    next_tile: AtomicUsize,
+}
+
+impl TileQueue {
+    /// Tiles covering `region` of the image, handed out from the top row down, matching how the
+    /// image is viewed
+    pub fn new(region: &Tile) -> TileQueue {
+        let mut tiles = Vec::new();
+        for y_start in (region.y_start..region.y_end).step_by(TILE_SIZE).rev() {
+            for x_start in (region.x_start..region.x_end).step_by(TILE_SIZE) {
+                tiles.push(Tile {
+                    x_start,
+                    y_start,
+                    x_end: (x_start + TILE_SIZE).min(region.x_end),
+                    y_end: (y_start + TILE_SIZE).min(region.y_end),
+                });
+            }
+        }
+        TileQueue {
+            tiles,
+            next_tile: AtomicUsize::new(0),
+        }
+    }
+
+    /// The next tile nobody has rendered yet along with its index in the queue, `None` once all
+    /// tiles are taken
+    pub fn next_tile(&self) -> Option<(usize, Tile)> {
+        let index = self.next_tile.fetch_add(1, Ordering::Relaxed);
+        self.tiles.get(index).map(|tile| (index, *tile))
+    }
+
+    /// Whether every tile has been handed out
+    pub fn is_empty(&self) -> bool {
+        self.next_tile.load(Ordering::Relaxed) >= self.tiles.len()
+    }
+}
@@ -18,4 +18,9 @@ impl ColorRgb8 {
             b: (256.0f32 * color_rgb.z.clamp(0.0, 0.999)) as u8,
         }
     }
+}
+
+/// Relative luminance of a linear Rec. 709 color
+pub fn luminance(color: &ColorRgbF) -> f32 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
 }
\ No newline at end of file
@@ -1,3 +1,4 @@
 pub mod color;
+pub mod onb;
 pub mod rehnda_math;
 pub mod ray;
\ No newline at end of file
@@ -0,0 +1,27 @@
+use crate::core::rehnda_math::{Vec3Ext, Vec3f};
+
+/// Orthonormal basis, used to move directions sampled about the z axis onto an arbitrary axis
+#[derive(Debug, Copy, Clone)]
+pub struct Onb {
+    pub u: Vec3f,
+    pub v: Vec3f,
+    pub w: Vec3f,
+}
+
+impl Onb {
+    pub fn build_from_w(n: &Vec3f) -> Onb {
+        let w = n.unit_vector();
+        let a = if w.x.abs() > 0.9 { Vec3f::Y } else { Vec3f::X };
+        let v = w.cross(a).unit_vector();
+        let u = w.cross(v);
+        Onb {
+            u,
+            v,
+            w,
+        }
+    }
+
+    pub fn local(&self, a: &Vec3f) -> Vec3f {
+        a.x * self.u + a.y * self.v + a.z * self.w
+    }
+}
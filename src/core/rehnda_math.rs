@@ -1,27 +1,58 @@
 use glam::{Vec3A};
+use rand::{Rng as _, SeedableRng};
+use rand_pcg::Pcg32;
 
 pub type Vec3f = Vec3A;
 pub type Point3f = Vec3A;
 
-pub fn random_int_in_range(min: i32, max: i32) -> i32 {
-    (min as f32 + rand::random::<f32>() * (max - min) as f32) as i32
+/// Random number generator threaded through everything that samples during rendering. Each
+/// sample of each pixel gets its own generator from [sample_rng], so renders are reproducible.
+pub type Rng = Pcg32;
+
+/// Generator for sample `sample_index` of pixel `pixel_index`. The pixel picks the generator's
+/// state and the sample its stream, so the result doesn't depend on which thread renders the
+/// sample or in what order.
+pub fn sample_rng(seed: u64, pixel_index: u64, sample_index: u64) -> Rng {
+    Pcg32::new(hash_u64(seed ^ hash_u64(pixel_index)), sample_index)
+}
+
+/// Generator for randomness outside of rendering samples, such as procedurally generated scene content
+pub fn seeded_rng(seed: u64) -> Rng {
+    Pcg32::seed_from_u64(seed)
+}
+
+/// SplitMix64 finaliser, scrambling every bit of `value` into every bit of the result
+pub fn hash_u64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// FNV-1a hash of `bytes`, finalised with `hash_u64`. Stable across platforms and builds, unlike
+/// the standard library's hashers.
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    let hash = bytes.iter().fold(0xcbf29ce484222325u64, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+    hash_u64(hash)
 }
 
-pub fn random_in_range(min: f32, max: f32) -> f32 {
-    min + rand::random::<f32>() * (max - min)
+/// Random integer in the inclusive range `min..=max`
+pub fn random_int_in_range(rng: &mut Rng, min: i32, max: i32) -> i32 {
+    (min as f32 + random(rng) * (max - min + 1) as f32) as i32
 }
 
-pub fn random() -> f32 {
-    rand::random::<f32>()
+pub fn random_in_range(rng: &mut Rng, min: f32, max: f32) -> f32 {
+    min + random(rng) * (max - min)
+}
+
+pub fn random(rng: &mut Rng) -> f32 {
+    rng.gen::<f32>()
 }
 
 pub trait Vec3Ext {
     fn is_near_zero(&self) -> bool;
     fn unit_vector(&self) -> Vec3f;
-    fn random_vec_in_unit_sphere() -> Vec3f;
-    fn random_vec_in_unit_disk() -> Vec3f;
-    fn random_vec_in_range(min: f32, max: f32) -> Vec3f;
-    fn random_unit_vector() -> Vec3f;
+    fn random_vec_in_range(rng: &mut Rng, min: f32, max: f32) -> Vec3f;
     fn reflect(&self, normal: Vec3f) -> Vec3f;
     fn refract(&self, normal: Vec3f, refraction_ratio: f32) -> Vec3f;
 }
@@ -36,30 +67,8 @@ impl Vec3Ext for Vec3A {
         *self / self.length()
     }
 
-    fn random_vec_in_unit_sphere() -> Vec3f {
-        loop {
-            let candidate = Self::random_vec_in_range(-1f32, 1f32);
-            if candidate.length_squared() < 1f32 {
-                return candidate;
-            }
-        }
-    }
-
-    fn random_vec_in_unit_disk() -> Vec3f {
-        loop {
-            let candidate = Vec3f::new(random_in_range(-1.0, 1.0), random_in_range(-1.0, 1.0), 0.0);
-            if candidate.length_squared() < 1.0 {
-                return candidate;
-            }
-        }
-    }
-
-    fn random_vec_in_range(min: f32, max: f32) -> Vec3f {
-        Vec3f::new(random_in_range(min, max), random_in_range(min, max), random_in_range(min, max))
-    }
-
-    fn random_unit_vector() -> Vec3f {
-        Self::random_vec_in_unit_sphere()
+    fn random_vec_in_range(rng: &mut Rng, min: f32, max: f32) -> Vec3f {
+        Vec3f::new(random_in_range(rng, min, max), random_in_range(rng, min, max), random_in_range(rng, min, max))
     }
 
     fn reflect(&self, normal: Vec3f) -> Vec3f {
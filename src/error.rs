@@ -0,0 +1,62 @@
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Everything that can go wrong loading settings and building a scene from them, with messages
+/// meant for whoever wrote the files
+#[derive(Debug, Error)]
+pub enum RehndaError {
+    #[error("couldn't read {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("couldn't write {}: {source}", path.display())]
+    Write { path: PathBuf, source: io::Error },
+    #[error("couldn't write image {}: {source}", path.display())]
+    WriteImage { path: PathBuf, source: image::ImageError },
+    /// A settings or scene file that isn't valid RON or doesn't match the expected structure
+    #[error("{}:{line}:{column}: {message}", path.display())]
+    Parse { path: PathBuf, line: usize, column: usize, message: String },
+    #[error("invalid --set {assignment}: {message}")]
+    InvalidOverride { assignment: String, message: String },
+    #[error("invalid settings: {0}")]
+    InvalidSettings(String),
+    #[error("couldn't load texture {}: {source}", path.display())]
+    Texture { path: PathBuf, source: image::ImageError },
+    #[error("couldn't load environment map {}: {source}", path.display())]
+    EnvironmentMap { path: PathBuf, source: image::ImageError },
+    #[error("{}:{line}: {message}", path.display())]
+    InvalidMesh { path: PathBuf, line: usize, message: String },
+    #[error("invalid scene: {0}")]
+    InvalidScene(String),
+    #[error("material '{0}' is not defined in the scene file")]
+    UndefinedMaterial(String),
+    /// Objects with no area or volume, like zero radius spheres and rects with matching corners
+    #[error("invalid geometry: {0}")]
+    InvalidGeometry(String),
+    #[error("checkpoint {}: {message}", path.display())]
+    InvalidCheckpoint { path: PathBuf, message: String },
+}
+
+impl RehndaError {
+    /// Exit code for the command line: 2 for files and overrides that don't parse and settings
+    /// that don't make sense, like clap's usage errors, 3 for scenes and checkpoints that can't be
+    /// used and 4 for files that can't be read or written
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            RehndaError::Parse { .. } | RehndaError::InvalidOverride { .. } | RehndaError::InvalidSettings(_) => 2,
+            RehndaError::InvalidMesh { .. } | RehndaError::InvalidScene(_) | RehndaError::UndefinedMaterial(_)
+            | RehndaError::InvalidGeometry(_) | RehndaError::InvalidCheckpoint { .. } => 3,
+            RehndaError::Read { .. } | RehndaError::Write { .. } | RehndaError::WriteImage { .. } | RehndaError::Texture { .. }
+            | RehndaError::EnvironmentMap { .. } => 4,
+        }
+    }
+
+    /// Positions the RON error at its line and column in `path`
+    pub(crate) fn from_ron(path: PathBuf, error: ron::error::SpannedError) -> RehndaError {
+        RehndaError::Parse {
+            path,
+            line: error.position.line,
+            column: error.position.col,
+            message: error.code.to_string(),
+        }
+    }
+}
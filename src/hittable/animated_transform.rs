@@ -0,0 +1,171 @@
+use std::sync::Arc;
+use glam::{Mat3A, Mat4, Quat, Vec3};
+use serde::Deserialize;
+use crate::acceleration::aabb::Aabb;
+use crate::core::ray::Ray;
+use crate::core::rehnda_math::{Point3f, Vec3Ext, Vec3f};
+use crate::hittable::{HitResult, Hittable};
+use crate::hittable::transform::Transform;
+use crate::sampler::PixelSampler;
+
+/// Number of steps per keyframe the motion is split into when bounding the object
+const BOUNDING_BOX_STEPS: usize = 16;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Keyframe {
+    pub time: f32,
+    pub translation: Vec3f,
+    pub rotation: Quat,
+    pub scale: Vec3f,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Default)]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    /// Smoothstep easing so the object accelerates out of and decelerates into each keyframe
+    EaseInOut,
+}
+
+impl Interpolation {
+    fn apply(&self, s: f32) -> f32 {
+        match self {
+            Interpolation::Linear => s,
+            Interpolation::EaseInOut => s * s * (3.0 - 2.0 * s),
+        }
+    }
+}
+
+/// Moves a hittable through a sequence of scale/rotation/translation keyframes over time.
+///
+/// The transform is evaluated at each ray's time, so combined with the camera's shutter interval
+/// the object is motion blurred. Before the first and after the last keyframe the object holds still.
+pub struct AnimatedTransform {
+    contained_hittable: Arc<dyn Hittable>,
+    keyframes: Vec<Keyframe>,
+    interpolation: Interpolation,
+}
+
+impl AnimatedTransform {
+    pub fn new(hittable: Arc<dyn Hittable>, mut keyframes: Vec<Keyframe>, interpolation: Interpolation) -> AnimatedTransform {
+        assert!(!keyframes.is_empty(), "An animated transform needs at least one keyframe");
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        AnimatedTransform {
+            contained_hittable: hittable,
+            keyframes,
+            interpolation,
+        }
+    }
+
+    /// Applies the same animation to another hittable, e.g. to move the lights found within the
+    /// contained hittable
+    pub fn with_hittable(&self, hittable: Arc<dyn Hittable>) -> AnimatedTransform {
+        AnimatedTransform {
+            contained_hittable: hittable,
+            keyframes: self.keyframes.clone(),
+            interpolation: self.interpolation,
+        }
+    }
+
+    fn keyframe_at(&self, time: f32) -> Keyframe {
+        let first = self.keyframes[0];
+        let last = self.keyframes[self.keyframes.len() - 1];
+        if time <= first.time {
+            return first;
+        }
+        if time >= last.time {
+            return last;
+        }
+
+        let next_index = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+        let start = &self.keyframes[next_index - 1];
+        let end = &self.keyframes[next_index];
+        let s = self.interpolation.apply((time - start.time) / (end.time - start.time));
+        Keyframe {
+            time,
+            translation: start.translation.lerp(end.translation, s),
+            rotation: start.rotation.slerp(end.rotation, s),
+            scale: start.scale.lerp(end.scale, s),
+        }
+    }
+
+    fn object_to_world(keyframe: &Keyframe) -> Mat4 {
+        Mat4::from_scale_rotation_translation(Vec3::from(keyframe.scale), keyframe.rotation, Vec3::from(keyframe.translation))
+    }
+
+    fn world_to_object(keyframe: &Keyframe) -> Mat4 {
+        Mat4::from_scale(Vec3::from(keyframe.scale.recip()))
+            * Mat4::from_quat(keyframe.rotation.conjugate())
+            * Mat4::from_translation(-Vec3::from(keyframe.translation))
+    }
+
+    fn object_ray(world_to_object: &Mat4, ray: &Ray) -> Ray {
+        Ray {
+            origin: world_to_object.transform_point3a(ray.origin),
+            direction: world_to_object.transform_vector3a(ray.direction),
+            time: ray.time,
+        }
+    }
+}
+
+impl Hittable for AnimatedTransform {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult<'_>> {
+        let keyframe = self.keyframe_at(ray.time);
+        let world_to_object = AnimatedTransform::world_to_object(&keyframe);
+        self.contained_hittable.hit(&AnimatedTransform::object_ray(&world_to_object, ray), t_min, t_max).map(|hit| {
+            let normal_matrix = Mat3A::from_mat4(world_to_object).transpose();
+            HitResult {
+                hit_location: AnimatedTransform::object_to_world(&keyframe).transform_point3a(hit.hit_location),
+                normal: (normal_matrix * hit.normal).unit_vector(),
+                t: hit.t,
+                front_face: hit.front_face,
+                uv: hit.uv,
+                material: hit.material,
+            }
+        })
+    }
+
+    /// Encloses the object at evenly spaced steps through the motion and at every keyframe within
+    /// it, where the motion can change direction. Rotations sweep the object along arcs that bulge
+    /// out between steps, so each step's box is padded by the worst case distance between an arc
+    /// and its chord.
+    fn bounding_box(&self, time_0: f32, time_1: f32) -> Option<Aabb> {
+        let contained_box = self.contained_hittable.bounding_box(time_0, time_1)?;
+        let max_scale = self.keyframes.iter().map(|keyframe| keyframe.scale.abs().max_element()).fold(0.0, f32::max);
+        let radius = contained_box.min_corner.abs().max(contained_box.max_corner.abs()).length() * max_scale;
+
+        let num_steps = BOUNDING_BOX_STEPS * self.keyframes.len();
+        let mut times: Vec<f32> = (0..=num_steps)
+            .map(|step| time_0 + (time_1 - time_0) * step as f32 / num_steps as f32)
+            .chain([time_0, time_1])
+            .chain(self.keyframes.iter().map(|keyframe| keyframe.time).filter(|time| (time_0..=time_1).contains(time)))
+            .collect();
+        times.sort_by(f32::total_cmp);
+
+        let mut previous_keyframe = self.keyframe_at(times[0]);
+        let mut bounding_box = contained_box.transformed(&AnimatedTransform::object_to_world(&previous_keyframe));
+        for time in times.into_iter().skip(1) {
+            let keyframe = self.keyframe_at(time);
+            let arc_padding = radius * (1.0 - (previous_keyframe.rotation.angle_between(keyframe.rotation) / 2.0).cos());
+            let step_box = contained_box.transformed(&AnimatedTransform::object_to_world(&keyframe));
+            bounding_box = Aabb::surrounding_box(&bounding_box, &Aabb {
+                min_corner: step_box.min_corner - Vec3f::splat(arc_padding),
+                max_corner: step_box.max_corner + Vec3f::splat(arc_padding),
+            });
+            previous_keyframe = keyframe;
+        }
+        Some(bounding_box)
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f32 {
+        let world_to_object = AnimatedTransform::world_to_object(&self.keyframe_at(ray.time));
+        self.contained_hittable.pdf_value(&AnimatedTransform::object_ray(&world_to_object, ray))
+            * Transform::solid_angle_jacobian(&world_to_object, &ray.direction)
+    }
+
+    fn random_direction(&self, origin: &Point3f, time: f32, sampler: &mut PixelSampler) -> Vec3f {
+        let keyframe = self.keyframe_at(time);
+        let object_origin = AnimatedTransform::world_to_object(&keyframe).transform_point3a(*origin);
+        AnimatedTransform::object_to_world(&keyframe).transform_vector3a(self.contained_hittable.random_direction(&object_origin, time, sampler))
+    }
+}
@@ -3,7 +3,7 @@ use log::{error, warn};
 use crate::acceleration::aabb::Aabb;
 use crate::core::color::ColorRgbF;
 use crate::core::ray::Ray;
-use crate::core::rehnda_math::{random, Vec3f};
+use crate::core::rehnda_math::{hash_u64, Vec3f};
 use crate::hittable::{HitResult, Hittable};
 use crate::material::isotropic::IsotropicMaterial;
 use crate::material::Material;
@@ -33,13 +33,24 @@ impl ConstantMedium {
     }
 }
 
+/// Uniform number in the unit interval hashed from the ray, standing in for a random number so a
+/// hit stays a fixed function of the ray. Testing the same ray again, as light pdfs do, gives the
+/// same answer, and renders are reproducible without passing a generator into every hit test.
+fn ray_random(ray: &Ray) -> f32 {
+    let components = [ray.origin.x, ray.origin.y, ray.origin.z, ray.direction.x, ray.direction.y, ray.direction.z];
+    let hash = components.iter()
+        .fold(hash_u64(ray.time.to_bits() as u64), |hash, component| hash_u64(hash ^ component.to_bits() as u64));
+    // the top 24 bits fill an f32's mantissa exactly
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
 impl Hittable for ConstantMedium {
     ///
     /// This implementation does not support non convex volumes, as it expects to not
     /// hit itself again once it passes the boundary
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult<'_>> {
         let enable_debug = false;
-        let debugging = enable_debug && random() < 0.00001;
+        let debugging = enable_debug && ray_random(ray) < 0.00001;
 
         // do we ever encounter the medium?
         let mut hit_1 = self.boundary.hit(ray, f32::MIN, f32::MAX)?;
@@ -68,7 +79,7 @@ impl Hittable for ConstantMedium {
         let ray_length = ray.direction.length();
         let distance_inside_boundary = (hit_2.t - hit_1.t) * ray_length;
         // when does the ray hit the medium (based on density)
-        let hit_distance = self.neg_inv_density * random().ln();
+        let hit_distance = self.neg_inv_density * ray_random(ray).ln();
 
         // ray wasn't inside the medium long enough to hit it so passes through
         if hit_distance > distance_inside_boundary {
@@ -3,6 +3,7 @@ use crate::acceleration::aabb::Aabb;
 use crate::core::ray::Ray;
 use crate::core::rehnda_math::{Point3f, Vec3f};
 use crate::material::Material;
+use crate::sampler::PixelSampler;
 use crate::texture::Uv;
 
 #[derive(Debug, Copy, Clone)]
@@ -28,14 +29,34 @@ impl<'a> HitResult<'a> {
     }
 }
 
+/// Converts a uniform density over a surface of `area` into a density over solid angle as seen
+/// from the origin of `ray`, which hit the surface at `t` where its geometric normal is `surface_normal`
+pub fn solid_angle_pdf(ray: &Ray, t: f32, surface_normal: &Vec3f, area: f32) -> f32 {
+    let distance_squared = t * t * ray.direction.length_squared();
+    let cosine = (ray.direction.dot(*surface_normal) / (ray.direction.length() * surface_normal.length())).abs();
+    distance_squared / (cosine * area)
+}
+
 pub trait Hittable: Send + Sync {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult>;
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult<'_>>;
 
     fn bounding_box(&self, time_0: f32, time_1: f32) -> Option<Aabb>;
+
+    /// Probability density, with respect to solid angle at `ray.origin`, of [Hittable::random_direction]
+    /// choosing the direction of `ray`. Only hittables that can be used as lights need to implement this.
+    fn pdf_value(&self, _ray: &Ray) -> f32 {
+        0.0
+    }
+
+    /// Random direction from `origin` towards the surface of the hittable at `time`, used to aim
+    /// shadow rays at lights
+    fn random_direction(&self, _origin: &Point3f, _time: f32, _sampler: &mut PixelSampler) -> Vec3f {
+        Vec3f::X
+    }
 }
 
 impl Hittable for Vec<Arc<dyn Hittable>> {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult<'_>> {
         let mut closest_hit = t_max;
         let mut closest_hit_object: Option<HitResult> = None;
         for obj in self {
@@ -48,7 +69,19 @@ impl Hittable for Vec<Arc<dyn Hittable>> {
         closest_hit_object
     }
 
+    /// Box around all of the hittables, `None` if any of them is unbounded or there are none
     fn bounding_box(&self, time_0: f32, time_1: f32) -> Option<Aabb> {
-        todo!()
+        let mut boxes = self.iter().map(|hittable| hittable.bounding_box(time_0, time_1));
+        let first_box = boxes.next()??;
+        boxes.try_fold(first_box, |surrounding, bounding_box| Some(Aabb::surrounding_box(&surrounding, &bounding_box?)))
+    }
+
+    /// Picks one of the hittables uniformly, so the density is the average of their densities
+    fn pdf_value(&self, ray: &Ray) -> f32 {
+        self.iter().map(|hittable| hittable.pdf_value(ray)).sum::<f32>() / self.len() as f32
+    }
+
+    fn random_direction(&self, origin: &Point3f, time: f32, sampler: &mut PixelSampler) -> Vec3f {
+        self[sampler.get_index(self.len())].random_direction(origin, time, sampler)
     }
 }
\ No newline at end of file
@@ -1,10 +1,12 @@
 mod hittable;
 pub use hittable::*;
+pub mod animated_transform;
 pub mod box_hittable;
 pub mod constant_medium;
-pub mod rotate_y;
+pub mod moving_sphere;
 pub mod sphere;
-pub mod translate;
+pub mod transform;
+pub mod triangle_mesh;
 pub mod xy_rect;
 pub mod xz_rect;
 pub mod yz_rect;
\ No newline at end of file
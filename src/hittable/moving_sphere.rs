@@ -0,0 +1,86 @@
+use std::sync::Arc;
+use crate::acceleration::aabb::Aabb;
+use crate::core::ray::Ray;
+use crate::core::rehnda_math::{Point3f, Vec3f};
+use crate::hittable::{HitResult, Hittable};
+use crate::hittable::sphere::Sphere;
+use crate::material::Material;
+use crate::sampler::PixelSampler;
+
+/// Sphere whose centre moves linearly from `centre_0` at `time_0` to `centre_1` at `time_1`.
+/// Outside of that time range the sphere carries on along the same line.
+pub struct MovingSphere {
+    pub centre_0: Point3f,
+    pub centre_1: Point3f,
+    pub time_0: f32,
+    pub time_1: f32,
+    pub radius: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl MovingSphere {
+    pub fn centre(&self, time: f32) -> Point3f {
+        self.centre_0 + ((time - self.time_0) / (self.time_1 - self.time_0)) * (self.centre_1 - self.centre_0)
+    }
+}
+
+impl Hittable for MovingSphere {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult<'_>> {
+        let centre = self.centre(ray.time);
+        let oc = ray.origin - centre;
+        let a = ray.direction.length_squared();
+        let half_b = oc.dot(ray.direction);
+        let c = oc.length_squared() - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0f32 {
+            return None;
+        }
+
+        let sqrt_dist = discriminant.sqrt();
+        let ia = 1f32 / a;
+        let mut root = (-half_b - sqrt_dist) * ia;
+        if root < t_min || root > t_max {
+            root = (-half_b + sqrt_dist) * ia;
+            if root < t_min || root > t_max {
+                return None;
+            }
+        }
+
+        let hit_location = ray.at(root);
+        let outward_normal = (hit_location - centre) / self.radius;
+        let (normal, front_face) = HitResult::is_hit_front_face(&ray.direction, &outward_normal);
+
+        Some(HitResult {
+            hit_location,
+            normal,
+            t: root,
+            front_face,
+            uv: Sphere::get_unit_sphere_uv(&outward_normal),
+            material: &*self.material,
+        })
+    }
+
+    fn bounding_box(&self, time_0: f32, time_1: f32) -> Option<Aabb> {
+        let radius = Vec3f::splat(self.radius);
+        let box_0 = Aabb {
+            min_corner: self.centre(time_0) - radius,
+            max_corner: self.centre(time_0) + radius,
+        };
+        let box_1 = Aabb {
+            min_corner: self.centre(time_1) - radius,
+            max_corner: self.centre(time_1) + radius,
+        };
+        Some(Aabb::surrounding_box(&box_0, &box_1))
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f32 {
+        match self.hit(ray, 0.001, f32::MAX) {
+            Some(_) => Sphere::cone_pdf(&self.centre(ray.time), self.radius, &ray.origin),
+            None => 0.0,
+        }
+    }
+
+    fn random_direction(&self, origin: &Point3f, time: f32, sampler: &mut PixelSampler) -> Vec3f {
+        Sphere::random_cone_direction(&self.centre(time), self.radius, origin, sampler)
+    }
+}
@@ -2,9 +2,11 @@ use std::f32::consts::PI;
 use std::sync::Arc;
 use crate::acceleration::aabb::Aabb;
 use crate::core::ray::Ray;
+use crate::core::onb::Onb;
 use crate::core::rehnda_math::{Point3f, Vec3f};
 use crate::hittable::{HitResult, Hittable};
 use crate::material::Material;
+use crate::sampler::{PixelSampler, warp};
 use crate::texture::Uv;
 
 pub struct Sphere {
@@ -14,7 +16,7 @@ pub struct Sphere {
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult<'_>> {
         let oc = ray.origin - self.centre;
         let a = ray.direction.length_squared();
         let half_b = oc.dot(ray.direction);
@@ -54,12 +56,51 @@ impl Hittable for Sphere {
             max_corner: self.centre + Vec3f::splat(self.radius),
         })
     }
+
+    fn pdf_value(&self, ray: &Ray) -> f32 {
+        match self.hit(ray, 0.001, f32::MAX) {
+            Some(_) => Sphere::cone_pdf(&self.centre, self.radius, &ray.origin),
+            None => 0.0,
+        }
+    }
+
+    fn random_direction(&self, origin: &Point3f, _time: f32, sampler: &mut PixelSampler) -> Vec3f {
+        Sphere::random_cone_direction(&self.centre, self.radius, origin, sampler)
+    }
 }
 
 impl Sphere {
-    fn get_unit_sphere_uv(surface_point: &Point3f) -> Uv {
+    pub fn get_unit_sphere_uv(surface_point: &Point3f) -> Uv {
         let theta = (-surface_point.y).acos();
         let phi = (-surface_point.z).atan2(surface_point.x) + PI;
         Uv::new(phi / (2f32 * PI), theta / PI)
     }
+
+    /// Density of [Sphere::random_cone_direction] over solid angle, which is uniform over the cone
+    /// the sphere subtends from `origin`
+    pub fn cone_pdf(centre: &Point3f, radius: f32, origin: &Point3f) -> f32 {
+        let distance_squared = (*centre - *origin).length_squared();
+        if distance_squared <= radius * radius {
+            return 1.0 / (4.0 * PI);
+        }
+        let cos_theta_max = (1.0 - radius * radius / distance_squared).sqrt();
+        1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+    }
+
+    /// Uniformly samples a direction within the cone a sphere subtends from `origin`. From inside
+    /// the sphere every direction hits it, so directions are sampled over the whole sphere instead.
+    pub fn random_cone_direction(centre: &Point3f, radius: f32, origin: &Point3f, sampler: &mut PixelSampler) -> Vec3f {
+        let direction = *centre - *origin;
+        let distance_squared = direction.length_squared();
+        if distance_squared <= radius * radius {
+            return warp::uniform_sphere(sampler.get_2d());
+        }
+
+        let cos_theta_max = (1.0 - radius * radius / distance_squared).sqrt();
+        let u = sampler.get_2d();
+        let z = 1.0 + u.x * (cos_theta_max - 1.0);
+        let phi = 2.0 * PI * u.y;
+        let sin_theta = (1.0 - z * z).sqrt();
+        Onb::build_from_w(&direction).local(&Vec3f::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z))
+    }
 }
\ No newline at end of file
@@ -0,0 +1,120 @@
+use std::sync::Arc;
+use glam::{Mat3A, Mat4, Vec3};
+use crate::acceleration::aabb::Aabb;
+use crate::core::ray::Ray;
+use crate::core::rehnda_math::{Point3f, Vec3Ext, Vec3f};
+use crate::hittable::{HitResult, Hittable};
+use crate::sampler::PixelSampler;
+
+/// Instances a hittable with an arbitrary affine transformation.
+///
+/// Rays are moved into the object space of the contained hittable with the inverse matrix, and
+/// hit normals are moved back to world space with the inverse-transpose so they stay perpendicular
+/// to the surface under non-uniform scaling.
+///
+/// Transforms are composed with the builder style helpers, which apply in the order called, e.g.
+/// `Transform::new(hittable).rotate(&Vec3f::Y, 15.0).translate(&offset)` rotates then translates.
+pub struct Transform {
+    contained_hittable: Arc<dyn Hittable>,
+    object_to_world: Mat4,
+    world_to_object: Mat4,
+    normal_matrix: Mat3A,
+}
+
+impl Transform {
+    pub fn new(hittable: Arc<dyn Hittable>) -> Transform {
+        Transform::from_matrix(hittable, Mat4::IDENTITY)
+    }
+
+    pub fn from_matrix(hittable: Arc<dyn Hittable>, object_to_world: Mat4) -> Transform {
+        let world_to_object = object_to_world.inverse();
+        Transform {
+            contained_hittable: hittable,
+            object_to_world,
+            world_to_object,
+            normal_matrix: Mat3A::from_mat4(world_to_object).transpose(),
+        }
+    }
+
+    /// Applies the same transformation to another hittable, e.g. to place the lights found within
+    /// the contained hittable
+    pub fn with_hittable(&self, hittable: Arc<dyn Hittable>) -> Transform {
+        Transform {
+            contained_hittable: hittable,
+            ..*self
+        }
+    }
+
+    /// Applies `matrix` after the transformations already held by this transform.
+    pub fn then(self, matrix: Mat4) -> Transform {
+        let object_to_world = matrix * self.object_to_world;
+        Transform::from_matrix(self.contained_hittable, object_to_world)
+    }
+
+    pub fn translate(self, offset: &Vec3f) -> Transform {
+        self.then(Mat4::from_translation(Vec3::from(*offset)))
+    }
+
+    /// Rotates counter-clockwise by `angle` degrees about `axis` through the origin.
+    pub fn rotate(self, axis: &Vec3f, angle: f32) -> Transform {
+        self.then(Mat4::from_axis_angle(Vec3::from(axis.unit_vector()), angle.to_radians()))
+    }
+
+    pub fn scale(self, scale: &Vec3f) -> Transform {
+        self.then(Mat4::from_scale(Vec3::from(*scale)))
+    }
+
+    /// Places the object at `from` with its -z axis pointing towards `to` and its +y axis
+    /// as close to `up` as possible.
+    pub fn look_at(self, from: &Point3f, to: &Point3f, up: &Vec3f) -> Transform {
+        self.then(Mat4::look_at_rh(Vec3::from(*from), Vec3::from(*to), Vec3::from(*up)).inverse())
+    }
+
+    /// The transform is affine so the ray parameter t is the same in both spaces
+    fn object_ray(&self, ray: &Ray) -> Ray {
+        Ray {
+            origin: self.world_to_object.transform_point3a(ray.origin),
+            direction: self.world_to_object.transform_vector3a(ray.direction),
+            time: ray.time,
+        }
+    }
+
+    /// Factor converting a density over object space solid angle into one over world space solid
+    /// angle for the world space `direction`. Mapping the directions with the linear part `A` of
+    /// `world_to_object` and renormalising stretches solid angle by `|det A| / |A d|^3` for unit `d`.
+    pub fn solid_angle_jacobian(world_to_object: &Mat4, direction: &Vec3f) -> f32 {
+        let linear = Mat3A::from_mat4(*world_to_object);
+        linear.determinant().abs() / (linear * direction.unit_vector()).length().powi(3)
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult<'_>> {
+        self.contained_hittable.hit(&self.object_ray(ray), t_min, t_max).map(|hit| {
+            // the inverse-transpose preserves the sign of the normal's dot product with the ray
+            // direction, so the contained hit's front face is still correct in world space
+            HitResult {
+                hit_location: self.object_to_world.transform_point3a(hit.hit_location),
+                normal: (self.normal_matrix * hit.normal).unit_vector(),
+                t: hit.t,
+                front_face: hit.front_face,
+                uv: hit.uv,
+                material: hit.material,
+            }
+        })
+    }
+
+    fn bounding_box(&self, time_0: f32, time_1: f32) -> Option<Aabb> {
+        self.contained_hittable.bounding_box(time_0, time_1)
+            .map(|contained_box| contained_box.transformed(&self.object_to_world))
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f32 {
+        self.contained_hittable.pdf_value(&self.object_ray(ray)) * Transform::solid_angle_jacobian(&self.world_to_object, &ray.direction)
+    }
+
+    fn random_direction(&self, origin: &Point3f, time: f32, sampler: &mut PixelSampler) -> Vec3f {
+        let object_origin = self.world_to_object.transform_point3a(*origin);
+        self.object_to_world.transform_vector3a(self.contained_hittable.random_direction(&object_origin, time, sampler))
+    }
+}
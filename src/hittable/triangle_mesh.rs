@@ -0,0 +1,281 @@
+use std::sync::Arc;
+use glam::Vec2;
+use crate::acceleration::aabb::Aabb;
+use crate::acceleration::bvh::{build_bvh, BvhBuilder, BvhLayout, BvhStats};
+use crate::core::ray::Ray;
+use crate::core::rehnda_math::{Point3f, Vec3Ext, Vec3f};
+use crate::hittable::{HitResult, Hittable, solid_angle_pdf};
+use crate::material::Material;
+use crate::sampler::distribution::Distribution1D;
+use crate::sampler::PixelSampler;
+use crate::texture::Uv;
+
+/// Vertex attribute storage shared by every triangle of a mesh.
+pub struct MeshData {
+    pub positions: Vec<Point3f>,
+    pub normals: Vec<Vec3f>,
+    pub uvs: Vec<Uv>,
+}
+
+/// Indices into the attribute arrays of a [MeshData] for a single corner of a triangle.
+#[derive(Debug, Copy, Clone)]
+pub struct MeshVertex {
+    pub position: usize,
+    pub normal: Option<usize>,
+    pub uv: Option<usize>,
+}
+
+pub struct Triangle {
+    mesh: Arc<MeshData>,
+    vertices: [MeshVertex; 3],
+    material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(mesh: Arc<MeshData>, vertices: [MeshVertex; 3], material: Arc<dyn Material>) -> Triangle {
+        Triangle {
+            mesh,
+            vertices,
+            material,
+        }
+    }
+
+    fn position(&self, corner: usize) -> Point3f {
+        self.mesh.positions[self.vertices[corner].position]
+    }
+
+    /// Geometric normal with a length of twice the triangle's area
+    fn area_vector(&self) -> Vec3f {
+        let p0 = self.position(0);
+        (self.position(1) - p0).cross(self.position(2) - p0)
+    }
+
+    /// Uniformly distributed point on the triangle, folding samples that land outside it back in
+    fn sample_point(&self, u: Vec2) -> Point3f {
+        let (mut b1, mut b2) = (u.x, u.y);
+        if b1 + b2 > 1.0 {
+            b1 = 1.0 - b1;
+            b2 = 1.0 - b2;
+        }
+        let p0 = self.position(0);
+        p0 + b1 * (self.position(1) - p0) + b2 * (self.position(2) - p0)
+    }
+
+    fn shading_normal(&self, geometric_normal: &Vec3f, b0: f32, b1: f32, b2: f32) -> Vec3f {
+        match (self.vertices[0].normal, self.vertices[1].normal, self.vertices[2].normal) {
+            (Some(n0), Some(n1), Some(n2)) => {
+                let interpolated = b0 * self.mesh.normals[n0] + b1 * self.mesh.normals[n1] + b2 * self.mesh.normals[n2];
+                if interpolated.is_near_zero() {
+                    *geometric_normal
+                } else {
+                    interpolated.unit_vector()
+                }
+            }
+            _ => *geometric_normal,
+        }
+    }
+
+    fn uv(&self, b0: f32, b1: f32, b2: f32) -> Uv {
+        match (self.vertices[0].uv, self.vertices[1].uv, self.vertices[2].uv) {
+            (Some(t0), Some(t1), Some(t2)) => {
+                let (uv0, uv1, uv2) = (self.mesh.uvs[t0], self.mesh.uvs[t1], self.mesh.uvs[t2]);
+                Uv::new(b0 * uv0.u + b1 * uv1.u + b2 * uv2.u, b0 * uv0.v + b1 * uv1.v + b2 * uv2.v)
+            }
+            _ => Uv::new(b1, b2),
+        }
+    }
+}
+
+impl Hittable for Triangle {
+    /// Möller–Trumbore ray/triangle intersection
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult<'_>> {
+        let p0 = self.position(0);
+        let edge_1 = self.position(1) - p0;
+        let edge_2 = self.position(2) - p0;
+
+        let p_vec = ray.direction.cross(edge_2);
+        let determinant = edge_1.dot(p_vec);
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+        let inv_determinant = 1.0 / determinant;
+
+        let t_vec = ray.origin - p0;
+        let b1 = t_vec.dot(p_vec) * inv_determinant;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+
+        let q_vec = t_vec.cross(edge_1);
+        let b2 = ray.direction.dot(q_vec) * inv_determinant;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let t = edge_2.dot(q_vec) * inv_determinant;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let b0 = 1.0 - b1 - b2;
+        let geometric_normal = edge_1.cross(edge_2).unit_vector();
+        let outward_normal = self.shading_normal(&geometric_normal, b0, b1, b2);
+        let (normal, front_face) = HitResult::is_hit_front_face(&ray.direction, &outward_normal);
+
+        Some(HitResult {
+            hit_location: ray.at(t),
+            normal,
+            t,
+            front_face,
+            uv: self.uv(b0, b1, b2),
+            material: &*self.material,
+        })
+    }
+
+    fn bounding_box(&self, _time_0: f32, _time_1: f32) -> Option<Aabb> {
+        let (p0, p1, p2) = (self.position(0), self.position(1), self.position(2));
+        // pad the box so axis aligned triangles don't produce a zero width box
+        Some(Aabb {
+            min_corner: p0.min(p1).min(p2) - Vec3f::splat(0.0001),
+            max_corner: p0.max(p1).max(p2) + Vec3f::splat(0.0001),
+        })
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f32 {
+        match self.hit(ray, 0.001, f32::MAX) {
+            Some(hit) => {
+                let area_vector = self.area_vector();
+                solid_angle_pdf(ray, hit.t, &area_vector, 0.5 * area_vector.length())
+            }
+            None => 0.0,
+        }
+    }
+
+    fn random_direction(&self, origin: &Point3f, _time: f32, sampler: &mut PixelSampler) -> Vec3f {
+        self.sample_point(sampler.get_2d()) - *origin
+    }
+}
+
+/// Face of a [MeshLight]. Hits report the geometric normal, which the density of points on the
+/// face is measured against, rather than the shading normal.
+struct LightFace(Arc<Triangle>);
+
+impl Hittable for LightFace {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult<'_>> {
+        self.0.hit(ray, t_min, t_max).map(|hit| HitResult {
+            normal: self.0.area_vector(),
+            ..hit
+        })
+    }
+
+    fn bounding_box(&self, time_0: f32, time_1: f32) -> Option<Aabb> {
+        self.0.bounding_box(time_0, time_1)
+    }
+}
+
+/// The emissive faces of a mesh sampled as a single light. Faces are chosen in proportion to their
+/// area so sampled points are spread uniformly over the whole emissive surface, and the density of
+/// a direction is found with a BVH over the faces rather than by asking each face in turn.
+pub struct MeshLight {
+    faces: Vec<Arc<Triangle>>,
+    faces_bvh: Arc<dyn Hittable>,
+    area_distribution: Distribution1D,
+    area: f32,
+}
+
+impl MeshLight {
+    /// `None` if the faces have no area to sample
+    fn new(faces: Vec<Arc<Triangle>>, bvh_builder: BvhBuilder, bvh_layout: BvhLayout) -> Option<MeshLight> {
+        let areas: Vec<f32> = faces.iter().map(|face| 0.5 * face.area_vector().length()).collect();
+        let area = areas.iter().sum::<f32>();
+        if area <= 0.0 {
+            return None;
+        }
+        let light_faces: Vec<Arc<dyn Hittable>> = faces.iter().map(|face| Arc::new(LightFace(face.clone())) as Arc<dyn Hittable>).collect();
+        let (faces_bvh, _) = build_bvh(light_faces.as_slice(), 0.0, 1.0, bvh_builder, bvh_layout);
+        Some(MeshLight {
+            faces,
+            faces_bvh,
+            area_distribution: Distribution1D::new(&areas),
+            area,
+        })
+    }
+}
+
+impl Hittable for MeshLight {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult<'_>> {
+        self.faces_bvh.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, time_0: f32, time_1: f32) -> Option<Aabb> {
+        self.faces_bvh.bounding_box(time_0, time_1)
+    }
+
+    /// Any of the faces along the ray could have been sampled, e.g. the far side of a closed mesh,
+    /// so their densities are summed by stepping through the hits
+    fn pdf_value(&self, ray: &Ray) -> f32 {
+        let mut pdf = 0.0;
+        let mut t_min = 0.001;
+        while let Some(hit) = self.faces_bvh.hit(ray, t_min, f32::MAX) {
+            pdf += solid_angle_pdf(ray, hit.t, &hit.normal, self.area);
+            // step past the face just hit, and any neighbour sharing the edge it was hit on
+            t_min = hit.t * (1.0 + 1e-4);
+        }
+        pdf
+    }
+
+    fn random_direction(&self, origin: &Point3f, _time: f32, sampler: &mut PixelSampler) -> Vec3f {
+        let (_, face, _) = self.area_distribution.sample_continuous(sampler.get_1d());
+        self.faces[face].sample_point(sampler.get_2d()) - *origin
+    }
+}
+
+/// A collection of triangles sharing vertex storage, accelerated by a BVH over the faces.
+pub struct TriangleMesh {
+    faces: Arc<dyn Hittable>,
+    light: Option<Arc<dyn Hittable>>,
+    bvh_stats: BvhStats,
+}
+
+impl TriangleMesh {
+    pub fn new(mesh: Arc<MeshData>, faces: Vec<([MeshVertex; 3], Arc<dyn Material>)>, bvh_builder: BvhBuilder, bvh_layout: BvhLayout) -> TriangleMesh {
+        let mut emissive_faces = Vec::new();
+        let triangles: Vec<Arc<dyn Hittable>> = faces.into_iter()
+            .map(|(vertices, material)| {
+                let is_emissive = material.is_emissive();
+                let triangle = Arc::new(Triangle::new(mesh.clone(), vertices, material));
+                if is_emissive {
+                    emissive_faces.push(triangle.clone());
+                }
+                triangle as Arc<dyn Hittable>
+            })
+            .collect();
+
+        let (faces, bvh_stats) = build_bvh(triangles.as_slice(), 0.0, 1.0, bvh_builder, bvh_layout);
+        let light = MeshLight::new(emissive_faces, bvh_builder, bvh_layout).map(|light| Arc::new(light) as Arc<dyn Hittable>);
+        TriangleMesh {
+            faces,
+            light,
+            bvh_stats,
+        }
+    }
+
+    /// The triangles with an emissive material as a single light, if there are any
+    pub fn light(&self) -> Option<&Arc<dyn Hittable>> {
+        self.light.as_ref()
+    }
+
+    pub fn bvh_stats(&self) -> &BvhStats {
+        &self.bvh_stats
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult<'_>> {
+        self.faces.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, time_0: f32, time_1: f32) -> Option<Aabb> {
+        self.faces.bounding_box(time_0, time_1)
+    }
+}
@@ -3,8 +3,9 @@ use crate::acceleration::aabb::Aabb;
 use crate::core::ray::Ray;
 use crate::core::rehnda_math::{Point3f, Vec3f};
 
-use crate::hittable::{HitResult, Hittable};
+use crate::hittable::{HitResult, Hittable, solid_angle_pdf};
 use crate::material::Material;
+use crate::sampler::PixelSampler;
 use crate::texture::Uv;
 
 pub struct XyRect {
@@ -31,9 +32,9 @@ impl XyRect {
 
 
 impl Hittable for XyRect {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitResult<'_>> {
         let t = (self.k - ray.origin.z) / ray.direction.z;
-        if t < t_min || t > t_max {
+        if !(t_min..=t_max).contains(&t) {
             return None
         }
 
@@ -56,10 +57,22 @@ impl Hittable for XyRect {
         })
     }
 
-    fn bounding_box(&self, time_0: f32, time_1: f32) -> Option<Aabb> {
+    fn bounding_box(&self, _time_0: f32, _time_1: f32) -> Option<Aabb> {
         Some(Aabb {
             min_corner: Point3f::new(self.x0, self.y0, self.k - 0.0001),
             max_corner: Point3f::new(self.x1, self.y1, self.k + 0.0001),
         })
     }
-}
\ No newline at end of file
+
+    fn pdf_value(&self, ray: &Ray) -> f32 {
+        match self.hit(ray, 0.001, f32::MAX) {
+            Some(hit) => solid_angle_pdf(ray, hit.t, &hit.normal, (self.x1 - self.x0) * (self.y1 - self.y0)),
+            None => 0.0,
+        }
+    }
+
+    fn random_direction(&self, origin: &Point3f, _time: f32, sampler: &mut PixelSampler) -> Vec3f {
+        let point = sampler.get_2d();
+        Point3f::new(self.x0 + point.x * (self.x1 - self.x0), self.y0 + point.y * (self.y1 - self.y0), self.k) - *origin
+    }
+}
@@ -0,0 +1,231 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::core::color::ColorRgbF;
+use crate::image::image_buffer::ImageBuffer;
+
+/// Identifies checkpoint files and the version of their layout
+const MAGIC: &[u8; 8] = b"RHNDCKP3";
+/// Bytes of the magic and the five u64 header values
+const HEADER_SIZE: u64 = 8 + 5 * 8;
+/// Bytes stored per pixel: six f32 sums and the u64 sample count
+const PIXEL_RECORD_SIZE: u64 = 6 * 4 + 8;
+
+/// Everything needed to carry on a render: the raw sums of the samples taken so far, and what
+/// they were rendered from. Every sample's random numbers come from the seed, the pixel and the
+/// index of the sample, so the seed and the per pixel sample counts are the whole generator state.
+///
+/// Stored as the magic bytes, the settings hash, the seed, the width, the height and the number of
+/// merged seeds, followed by the merged seeds (u64) and then the weighted red, green and blue sums,
+/// the weight sum, the luminance and squared luminance sums (f32) and the sample count (u64) of
+/// every pixel, all little endian.
+pub struct Checkpoint {
+    /// Hash of the settings and scene affecting the rendered radiance, see `RehndaSettings::render_hash`
+    pub settings_hash: u64,
+    /// Seed the render carries on with when resumed
+    pub seed: u64,
+    /// Seeds of the other renders whose samples were merged into this one
+    pub merged_seeds: Vec<u64>,
+    pub image_buffer: ImageBuffer,
+}
+
+impl Checkpoint {
+    /// Writes the checkpoint next to `path` and then moves it over `path`, so an interrupted write
+    /// never leaves a truncated checkpoint in place of the previous one
+    pub fn write_to_file(&self, path: &Path) -> Result<(), Error> {
+        let temporary_path = temporary_path(path);
+        self.write_contents(&temporary_path)?;
+        fs::rename(&temporary_path, path)
+    }
+
+    fn write_contents(&self, path: &Path) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let buffer = &self.image_buffer;
+        writer.write_all(MAGIC)?;
+        for value in [self.settings_hash, self.seed, buffer.image_width as u64, buffer.image_height as u64, self.merged_seeds.len() as u64] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        for seed in &self.merged_seeds {
+            writer.write_all(&seed.to_le_bytes())?;
+        }
+        for index in 0..buffer.pixels.len() {
+            let pixel = buffer.pixels[index];
+            for value in [pixel.x, pixel.y, pixel.z, buffer.weights[index], buffer.luminances[index], buffer.squared_luminances[index]] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            writer.write_all(&(buffer.sample_counts[index] as u64).to_le_bytes())?;
+        }
+        writer.into_inner().map_err(|error| error.into_error())?.sync_all()
+    }
+
+    /// Files that aren't checkpoints, or whose size doesn't match the resolution in their header,
+    /// fail with [ErrorKind::InvalidData]
+    pub fn read_from_file(path: &Path) -> Result<Checkpoint, Error> {
+        let file = File::open(path)?;
+        let file_size = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a rehnda checkpoint"));
+        }
+        let settings_hash = read_u64(&mut reader)?;
+        let seed = read_u64(&mut reader)?;
+        let image_width = read_u64(&mut reader)? as usize;
+        let image_height = read_u64(&mut reader)? as usize;
+        let num_merged_seeds = read_u64(&mut reader)?;
+        // checked before allocating the image, so a corrupt header can't ask for any amount of memory
+        let expected_size = (image_width as u64).checked_mul(image_height as u64)
+            .and_then(|num_pixels| num_pixels.checked_mul(PIXEL_RECORD_SIZE))
+            .and_then(|pixels_size| pixels_size.checked_add(HEADER_SIZE))
+            .and_then(|size| num_merged_seeds.checked_mul(8).and_then(|seeds_size| size.checked_add(seeds_size)));
+        if expected_size != Some(file_size) {
+            return Err(Error::new(ErrorKind::InvalidData, format!("is {} bytes, which doesn't match its {}x{} resolution", file_size, image_width, image_height)));
+        }
+
+        let merged_seeds = (0..num_merged_seeds).map(|_| read_u64(&mut reader)).collect::<Result<_, _>>()?;
+        let mut image_buffer = ImageBuffer::new(image_width, image_height);
+        for index in 0..image_width * image_height {
+            image_buffer.pixels[index] = ColorRgbF::new(read_f32(&mut reader)?, read_f32(&mut reader)?, read_f32(&mut reader)?);
+            image_buffer.weights[index] = read_f32(&mut reader)?;
+            image_buffer.luminances[index] = read_f32(&mut reader)?;
+            image_buffer.squared_luminances[index] = read_f32(&mut reader)?;
+            image_buffer.sample_counts[index] = read_u64(&mut reader)? as usize;
+        }
+        Ok(Checkpoint { settings_hash, seed, merged_seeds, image_buffer })
+    }
+
+    /// Adds the samples of a checkpoint rendered from the same settings, e.g. on another machine
+    /// with a different seed. The merged checkpoint keeps this checkpoint's seed.
+    ///
+    /// Renders with the same seed take identical samples, so checkpoints sharing a seed can only
+    /// be merged where they sampled different pixels, like the crop windows of one image.
+    pub fn merge(&mut self, other: &Checkpoint) -> Result<(), Error> {
+        if other.settings_hash != self.settings_hash {
+            return Err(Error::new(ErrorKind::InvalidInput, "checkpoints were rendered with different settings or scenes"));
+        }
+        if (other.image_buffer.image_width, other.image_buffer.image_height) != (self.image_buffer.image_width, self.image_buffer.image_height) {
+            return Err(Error::new(ErrorKind::InvalidInput, "checkpoints have different resolutions"));
+        }
+        if let Some(shared_seed) = other.seeds().find(|seed| self.seeds().any(|own_seed| own_seed == *seed)) {
+            let overlaps = self.image_buffer.sample_counts.iter().zip(&other.image_buffer.sample_counts)
+                .any(|(count, other_count)| *count > 0 && *other_count > 0);
+            if overlaps {
+                return Err(Error::new(ErrorKind::InvalidInput, format!("checkpoints both sampled the same pixels with seed {}, so the same samples would be counted twice", shared_seed)));
+            }
+        }
+
+        self.image_buffer.add_buffer_at(0, 0, &other.image_buffer);
+        for seed in other.seeds() {
+            if !self.seeds().any(|own_seed| own_seed == seed) {
+                self.merged_seeds.push(seed);
+            }
+        }
+        Ok(())
+    }
+
+    /// Seeds of every render whose samples are in the checkpoint
+    fn seeds(&self) -> impl Iterator<Item = u64> + '_ {
+        std::iter::once(self.seed).chain(self.merged_seeds.iter().copied())
+    }
+}
+
+/// `path` with `.tmp` added to the end of its file name
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().map_or_else(OsString::new, OsString::from);
+    file_name.push(".tmp");
+    path.with_file_name(file_name)
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, Error> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32(reader: &mut impl Read) -> Result<f32, Error> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec2;
+    use super::*;
+    use crate::image::filter::ReconstructionFilter;
+
+    fn checkpoint(seed: u64, pixels: &[(usize, usize)]) -> Checkpoint {
+        let mut image_buffer = ImageBuffer::new(3, 2);
+        for (i, &(x, y)) in pixels.iter().enumerate() {
+            let color = ColorRgbF::new(0.25 * i as f32, 1.5, 3.0 + x as f32);
+            image_buffer.splat_color_sample(x, y, Vec2::new(0.3, 0.6), color, &ReconstructionFilter::Tent { radius: 1.0 });
+        }
+        Checkpoint { settings_hash: 0xfeed_beef, seed, merged_seeds: vec![7, 11], image_buffer }
+    }
+
+    fn test_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rehnda_checkpoint_{}_{}.ckpt", name, std::process::id()))
+    }
+
+    #[test]
+    fn checkpoints_survive_a_round_trip() {
+        let path = test_path("round_trip");
+        let original = checkpoint(3, &[(0, 0), (2, 1), (2, 1), (1, 0)]);
+        original.write_to_file(&path).unwrap();
+        let read = Checkpoint::read_from_file(&path);
+        let temporary_left = temporary_path(&path).exists();
+        fs::remove_file(&path).unwrap();
+
+        let read = read.unwrap();
+        assert!(!temporary_left);
+        assert_eq!((read.settings_hash, read.seed, &read.merged_seeds), (original.settings_hash, original.seed, &original.merged_seeds));
+        let (buffer, original_buffer) = (&read.image_buffer, &original.image_buffer);
+        assert_eq!((buffer.image_width, buffer.image_height), (original_buffer.image_width, original_buffer.image_height));
+        assert_eq!(buffer.pixels, original_buffer.pixels);
+        assert_eq!(buffer.weights, original_buffer.weights);
+        assert_eq!(buffer.luminances, original_buffer.luminances);
+        assert_eq!(buffer.squared_luminances, original_buffer.squared_luminances);
+        assert_eq!(buffer.sample_counts, original_buffer.sample_counts);
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_whole_checkpoints() {
+        let path = test_path("invalid");
+        checkpoint(3, &[(1, 1)]).write_to_file(&path).unwrap();
+        let contents = fs::read(&path).unwrap();
+        let mut wrong_magic = contents.clone();
+        wrong_magic[0] = b'X';
+
+        let mut errors = Vec::new();
+        for bytes in [&contents[..contents.len() - 1], &contents[..20], &wrong_magic[..]] {
+            fs::write(&path, bytes).unwrap();
+            errors.push(Checkpoint::read_from_file(&path).err().map(|error| error.kind()));
+        }
+        fs::remove_file(&path).unwrap();
+
+        // a file too short for the header runs out before its size can be checked
+        assert_eq!(errors, [Some(ErrorKind::InvalidData), Some(ErrorKind::UnexpectedEof), Some(ErrorKind::InvalidData)]);
+    }
+
+    #[test]
+    fn merging_refuses_to_count_the_same_samples_twice() {
+        let mut merged = checkpoint(3, &[(0, 0)]);
+        assert!(merged.merge(&checkpoint(3, &[(0, 0)])).is_err());
+        assert!(merged.merge(&checkpoint(5, &[(1, 0)])).is_ok());
+        assert_eq!(merged.merged_seeds, [7, 11, 5]);
+        assert_eq!(merged.image_buffer.total_sample_count(), 2);
+
+        // the same seed can still be merged where it sampled other pixels, like another crop window
+        assert!(merged.merge(&checkpoint(3, &[(2, 1)])).is_ok());
+        assert_eq!(merged.merged_seeds, [7, 11, 5]);
+        // merged in seeds are checked too, not only the checkpoint's own
+        assert!(merged.merge(&checkpoint(5, &[(0, 0)])).is_err());
+
+        let mut other_settings = checkpoint(13, &[(0, 0)]);
+        other_settings.settings_hash += 1;
+        assert!(merged.merge(&other_settings).is_err());
+    }
+}
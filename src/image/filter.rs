@@ -0,0 +1,148 @@
+use std::f32::consts::PI;
+use glam::Vec2;
+use serde::Deserialize;
+
+/// Weighting of the samples around a pixel when working out its color. Each sample is splatted into
+/// every pixel whose center is within `radius` of it in both directions, weighted by the filter at
+/// its offset from the center. Wider filters trade sharpness for less aliasing and noise.
+#[derive(Debug, Copy, Clone, Deserialize, PartialEq)]
+pub enum ReconstructionFilter {
+    /// Equal weight everywhere within the radius. A radius of 0.5 keeps every sample in its own pixel.
+    Box { radius: f32 },
+    /// Weight falling linearly to zero at the radius
+    Tent { radius: f32 },
+    /// Gaussian of standard deviation `sigma`, shifted down to reach zero at the radius
+    Gaussian { radius: f32, sigma: f32 },
+    /// Mitchell and Netravali's cubic, with `b = c = 1/3` recommended. The negative lobes sharpen
+    /// edges, with a risk of ringing.
+    Mitchell { radius: f32, b: f32, c: f32 },
+    /// Sinc windowed by a sinc stretched by `tau`, which keeps the most detail but rings the most
+    Lanczos { radius: f32, tau: f32 },
+}
+
+impl Default for ReconstructionFilter {
+    fn default() -> Self {
+        ReconstructionFilter::Box { radius: 0.5 }
+    }
+}
+
+impl ReconstructionFilter {
+    pub fn radius(&self) -> f32 {
+        match *self {
+            ReconstructionFilter::Box { radius }
+            | ReconstructionFilter::Tent { radius }
+            | ReconstructionFilter::Gaussian { radius, .. }
+            | ReconstructionFilter::Mitchell { radius, .. }
+            | ReconstructionFilter::Lanczos { radius, .. } => radius,
+        }
+    }
+
+    /// How many pixels away from the pixel a sample was taken in it can contribute to
+    pub fn pixel_radius(&self) -> usize {
+        ((self.radius() + 0.5).ceil() as usize).saturating_sub(1)
+    }
+
+    /// Weight of a sample at `offset` from a pixel's center
+    pub fn evaluate(&self, offset: Vec2) -> f32 {
+        self.evaluate_1d(offset.x) * self.evaluate_1d(offset.y)
+    }
+
+    fn evaluate_1d(&self, x: f32) -> f32 {
+        let x = x.abs();
+        if x > self.radius() {
+            return 0.0;
+        }
+        match *self {
+            ReconstructionFilter::Box { .. } => 1.0,
+            ReconstructionFilter::Tent { radius } => 1.0 - x / radius,
+            ReconstructionFilter::Gaussian { radius, sigma } => (gaussian(x, sigma) - gaussian(radius, sigma)).max(0.0),
+            ReconstructionFilter::Mitchell { radius, b, c } => mitchell(2.0 * x / radius, b, c),
+            ReconstructionFilter::Lanczos { tau, .. } => sinc(x) * sinc(x / tau),
+        }
+    }
+}
+
+fn gaussian(x: f32, sigma: f32) -> f32 {
+    (-x * x / (2.0 * sigma * sigma)).exp()
+}
+
+/// Mitchell-Netravali cubic over `0..=2`
+fn mitchell(x: f32, b: f32, c: f32) -> f32 {
+    let cubic = if x > 1.0 {
+        (-b - 6.0 * c) * x.powi(3) + (6.0 * b + 30.0 * c) * x.powi(2) + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)
+    } else {
+        (12.0 - 9.0 * b - 6.0 * c) * x.powi(3) + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2) + (6.0 - 2.0 * b)
+    };
+    cubic / 6.0
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-5 {
+        return 1.0;
+    }
+    (PI * x).sin() / (PI * x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters(radius: f32) -> [ReconstructionFilter; 5] {
+        [
+            ReconstructionFilter::Box { radius },
+            ReconstructionFilter::Tent { radius },
+            ReconstructionFilter::Gaussian { radius, sigma: 0.5 },
+            ReconstructionFilter::Mitchell { radius, b: 1.0 / 3.0, c: 1.0 / 3.0 },
+            ReconstructionFilter::Lanczos { radius, tau: 3.0 },
+        ]
+    }
+
+    #[test]
+    fn pixel_radius_reaches_every_pixel_a_sample_can_contribute_to() {
+        for (radius, expected) in [(0.25, 0), (0.5, 0), (0.51, 1), (1.0, 1), (1.5, 1), (1.51, 2), (2.0, 2), (3.0, 3)] {
+            let filter = ReconstructionFilter::Box { radius };
+            assert_eq!(filter.pixel_radius(), expected, "radius {}", radius);
+            // the closest a sample gets to the center of the pixel `k` pixels away is `k - 0.5`
+            let pixel_radius = filter.pixel_radius() as f32;
+            if pixel_radius > 0.0 {
+                assert!(filter.evaluate_1d(pixel_radius - 0.5 + 1e-4) > 0.0, "radius {}", radius);
+            }
+            assert_eq!(filter.evaluate_1d(pixel_radius + 0.5 + 1e-4), 0.0, "radius {}", radius);
+        }
+    }
+
+    #[test]
+    fn weights_are_symmetric_and_vanish_past_the_radius() {
+        for radius in [0.5, 1.5, 2.0] {
+            for filter in filters(radius) {
+                assert!(filter.evaluate(Vec2::ZERO) > 0.0, "{:?}", filter);
+                assert_eq!(filter.evaluate(Vec2::new(radius + 1e-3, 0.0)), 0.0, "{:?}", filter);
+                assert_eq!(filter.evaluate(Vec2::new(0.0, -radius - 1e-3)), 0.0, "{:?}", filter);
+                for offset in [Vec2::new(0.1, 0.2), Vec2::new(0.4, -0.3), Vec2::new(radius * 0.9, 0.0)] {
+                    let weight = filter.evaluate(offset);
+                    assert_eq!(weight, filter.evaluate(-offset), "{:?}", filter);
+                    assert_eq!(weight, filter.evaluate(Vec2::new(offset.y, offset.x)), "{:?}", filter);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn filters_have_their_documented_shapes() {
+        let radius = 2.0;
+        let [box_filter, tent, gaussian, mitchell, lanczos] = filters(radius);
+        assert_eq!(box_filter.evaluate(Vec2::new(1.9, -1.9)), 1.0);
+        assert!((tent.evaluate(Vec2::new(1.0, 0.0)) - 0.5).abs() < 1e-6);
+        assert!(gaussian.evaluate(Vec2::new(radius - 1e-4, 0.0)) < 1e-6);
+        assert!(lanczos.evaluate(Vec2::new(1.0, 0.0)).abs() < 1e-6);
+        assert!(lanczos.evaluate(Vec2::new(2.0, 0.0)).abs() < 1e-6);
+
+        // Mitchell's cubic integrates to one over its support of -2..2, which the radius is scaled to
+        let steps = 4000;
+        let integral = (0..steps)
+            .map(|i| mitchell.evaluate_1d(-radius + (i as f32 + 0.5) * 2.0 * radius / steps as f32))
+            .sum::<f32>() * 4.0 / steps as f32;
+        assert!((integral - 1.0).abs() < 1e-3, "integral {}", integral);
+        assert!(mitchell.evaluate(Vec2::new(1.5, 0.0)) < 0.0, "negative lobe missing");
+    }
+}
@@ -1,55 +1,118 @@
-use crate::core::color::ColorRgbF;
-use crate::core::rehnda_math::Vec3Ext;
+use glam::Vec2;
+use crate::core::color::{ColorRgbF, luminance};
+use crate::image::filter::ReconstructionFilter;
+use crate::image::tone_mapping::DisplaySettings;
+
+/// Smallest mean luminance used when estimating a pixel's error, so black pixels with a few
+/// bright samples don't need an unbounded number of samples to converge
+const MIN_ERROR_LUMINANCE: f32 = 1e-4;
 
 #[derive(Debug, Clone)]
 pub struct ImageBuffer {
     pub image_width: usize,
     pub image_height: usize,
-    pixels: Vec<ColorRgbF>
+    /// Filter weighted sum of the radiance of the samples splatted into each pixel
+    pub(super) pixels: Vec<ColorRgbF>,
+    /// Sum of the filter weights of the samples splatted into each pixel
+    pub(super) weights: Vec<f32>,
+    /// Sum of the luminance of the samples taken within each pixel, for estimating its variance
+    pub(super) luminances: Vec<f32>,
+    /// Sum of the squared luminance of the samples taken within each pixel
+    pub(super) squared_luminances: Vec<f32>,
+    /// Number of samples taken within each pixel
+    pub(super) sample_counts: Vec<usize>,
 }
 
 impl ImageBuffer {
     pub fn new(width: usize, height: usize) -> ImageBuffer {
-        let mut pixels = Vec::new();
-        pixels.resize(width * height, ColorRgbF::ZERO);
         ImageBuffer {
             image_width: width,
             image_height: height,
-            pixels
+            pixels: vec![ColorRgbF::ZERO; width * height],
+            weights: vec![0.0; width * height],
+            luminances: vec![0.0; width * height],
+            squared_luminances: vec![0.0; width * height],
+            sample_counts: vec![0; width * height],
         }
     }
 
-    pub fn write_color_sample(&mut self , x: usize, y: usize, color: ColorRgbF) {
-        self.pixels[x + self.image_width * y] += color;
-    }
+    /// Adds a sample taken at `offset` within pixel `(x, y)` to every pixel in reach of `filter`
+    /// that lies within the buffer
+    pub fn splat_color_sample(&mut self, x: usize, y: usize, offset: Vec2, color: ColorRgbF, filter: &ReconstructionFilter) {
+        let index = x + self.image_width * y;
+        let sample_luminance = luminance(&color);
+        self.luminances[index] += sample_luminance;
+        self.squared_luminances[index] += sample_luminance * sample_luminance;
+        self.sample_counts[index] += 1;
 
-    pub fn add_buffer(&mut self, other_buffer: &ImageBuffer) {
-        for x in 0..self.image_width {
-            for y in 0..self.image_height {
-                self.write_color_sample(x, y, other_buffer.pixels[x + self.image_width * y]);
+        let pixel_radius = filter.pixel_radius();
+        for splat_y in y.saturating_sub(pixel_radius)..(y + pixel_radius + 1).min(self.image_height) {
+            for splat_x in x.saturating_sub(pixel_radius)..(x + pixel_radius + 1).min(self.image_width) {
+                let pixel_offset = Vec2::new(splat_x as f32 - x as f32, splat_y as f32 - y as f32);
+                let weight = filter.evaluate(pixel_offset + Vec2::splat(0.5) - offset);
+                if weight != 0.0 {
+                    let splat_index = splat_x + self.image_width * splat_y;
+                    self.pixels[splat_index] += weight * color;
+                    self.weights[splat_index] += weight;
+                }
             }
         }
     }
 
-    pub fn get_color_sample_corrected(&self, x: usize, y: usize, num_samples: usize) -> ColorRgbF {
-        let color = self.pixels[x + self.image_width * y];
-        if color.is_near_zero() {
-            return ColorRgbF::ZERO;
+    /// Adds the samples of a smaller buffer covering the pixels from `(x_offset, y_offset)` onwards
+    pub fn add_buffer_at(&mut self, x_offset: usize, y_offset: usize, other_buffer: &ImageBuffer) {
+        for x in 0..other_buffer.image_width {
+            for y in 0..other_buffer.image_height {
+                let index = x_offset + x + self.image_width * (y_offset + y);
+                let other_index = x + other_buffer.image_width * y;
+                self.pixels[index] += other_buffer.pixels[other_index];
+                self.weights[index] += other_buffer.weights[other_index];
+                self.luminances[index] += other_buffer.luminances[other_index];
+                self.squared_luminances[index] += other_buffer.squared_luminances[other_index];
+                self.sample_counts[index] += other_buffer.sample_counts[other_index];
+            }
         }
+    }
 
-        let mut r = color.x;
-        let mut g = color.y;
-        let mut b = color.z;
+    pub fn sample_count(&self, x: usize, y: usize) -> usize {
+        self.sample_counts[x + self.image_width * y]
+    }
 
-        let scale: f32 = 1.0 / num_samples as f32;
-        r = ImageBuffer::perform_gamma_correction(scale * r);
-        g = ImageBuffer::perform_gamma_correction(scale * g);
-        b = ImageBuffer::perform_gamma_correction(scale * b);
+    pub fn total_sample_count(&self) -> usize {
+        self.sample_counts.iter().sum()
+    }
 
-        ColorRgbF::new(r, g, b)
+    /// Filtered radiance of the pixel, the weighted average of the samples splatted into it,
+    /// without any display transform or clamping
+    pub fn get_color_sample_linear(&self, x: usize, y: usize) -> ColorRgbF {
+        let index = x + self.image_width * y;
+        // filters with negative lobes can leave a pixel with no or negative total weight
+        if self.weights[index] <= 0.0 {
+            return ColorRgbF::ZERO;
+        }
+        self.pixels[index] / self.weights[index]
+    }
+
+    /// Color of the pixel for 8 bit output, the filtered radiance passed through the display transform
+    pub fn get_color_sample_corrected(&self, x: usize, y: usize, display_settings: &DisplaySettings) -> ColorRgbF {
+        display_settings.apply(self.get_color_sample_linear(x, y))
     }
 
-    fn perform_gamma_correction(value: f32) -> f32 {
-        return value.sqrt();
+    /// Estimated error of the average luminance of the samples taken within the pixel after gamma 2
+    /// encoding, roughly the noise a viewer sees as a fraction of the display's range. Infinite
+    /// until the pixel has two samples.
+    pub fn estimated_error(&self, x: usize, y: usize) -> f32 {
+        let index = x + self.image_width * y;
+        let sample_count = self.sample_counts[index];
+        if sample_count < 2 {
+            return f32::INFINITY;
+        }
+
+        let mean = self.luminances[index] / sample_count as f32;
+        let variance = (self.squared_luminances[index] / sample_count as f32 - mean * mean).max(0.0)
+            * sample_count as f32 / (sample_count - 1) as f32;
+        let standard_error = (variance / sample_count as f32).sqrt();
+        // the derivative of the square root scales the error into display space
+        standard_error / (2.0 * mean.max(MIN_ERROR_LUMINANCE).sqrt())
     }
 }
@@ -1,18 +1,21 @@
-use std::fs::OpenOptions;
-use std::io::{Error, Write};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Error, Write};
 use std::path::Path;
-use image::{DynamicImage, ImageFormat, ImageResult, Rgb, Rgb32FImage, RgbImage};
-use image::buffer::ConvertBuffer;
+use image::{DynamicImage, ImageFormat, ImageResult, Rgb, Rgb32FImage};
+use image::codecs::hdr::HdrEncoder;
 
-use crate::core::color::ColorRgb8;
+use crate::core::color::{ColorRgb8, ColorRgbF};
 use crate::image::image_buffer::ImageBuffer;
+use crate::image::tone_mapping::DisplaySettings;
 
 pub struct ImageFileWriter<'a> {
     pub output_file_path: &'a Path,
+    /// Display transform for 8 bit formats, floating point formats are written linear
+    pub display_settings: &'a DisplaySettings,
 }
 
 impl ImageFileWriter<'_> {
-    pub fn write_image_buffer_to_ppm(&self, image_buffer: &ImageBuffer, num_samples: usize) -> Result<(), Error> {
+    pub fn write_image_buffer_to_ppm(&self, image_buffer: &ImageBuffer) -> Result<(), Error> {
         let mut out_file = OpenOptions::new()
             .write(true)
             .create(true)
@@ -25,7 +28,7 @@ impl ImageFileWriter<'_> {
         writeln!(out_file, "255")?;
         for y in (0..image_buffer.image_height).rev() {
             for x in 0..image_buffer.image_width {
-                let color_sample = image_buffer.get_color_sample_corrected(x, y, num_samples);
+                let color_sample = image_buffer.get_color_sample_corrected(x, y, self.display_settings);
                 let color_8_bit = ColorRgb8::from_color_rgb_f(color_sample);
                 writeln!(out_file, "{} {} {}", color_8_bit.r, color_8_bit.g, color_8_bit.b)?;
             }
@@ -33,16 +36,39 @@ impl ImageFileWriter<'_> {
         Ok(())
     }
 
-    pub fn write_to_file(&self, image_buffer: &ImageBuffer, num_samples: usize) -> ImageResult<()> {
+    /// Writes the average of the samples taken at each pixel. OpenEXR (`.exr`) and
+    /// Radiance (`.hdr`) files store the linear radiance as floats, every other format stores the
+    /// display transformed color in 8 bits.
+    pub fn write_to_file(&self, image_buffer: &ImageBuffer) -> ImageResult<()> {
+        match ImageFormat::from_path(self.output_file_path) {
+            Ok(ImageFormat::OpenExr) => {
+                let out_image = Self::to_rgb32f_image(image_buffer, |x, y| image_buffer.get_color_sample_linear(x, y));
+                DynamicImage::ImageRgb32F(out_image).save(self.output_file_path)
+            }
+            Ok(ImageFormat::Hdr) => {
+                // the image crate can't save hdr files through `save`, so use the encoder directly
+                let out_image = Self::to_rgb32f_image(image_buffer, |x, y| image_buffer.get_color_sample_linear(x, y));
+                let out_file = BufWriter::new(File::create(self.output_file_path)?);
+                let pixels: Vec<Rgb<f32>> = out_image.pixels().copied().collect();
+                HdrEncoder::new(out_file).encode(&pixels, image_buffer.image_width, image_buffer.image_height)
+            }
+            _ => {
+                let out_image = Self::to_rgb32f_image(image_buffer, |x, y| image_buffer.get_color_sample_corrected(x, y, self.display_settings));
+                DynamicImage::ImageRgb32F(out_image).into_rgb8().save(self.output_file_path)
+            }
+        }
+    }
+
+    /// Image of the colors returned by `pixel_color` for each pixel, flipped so the first row is
+    /// the top of the image
+    fn to_rgb32f_image(image_buffer: &ImageBuffer, pixel_color: impl Fn(usize, usize) -> ColorRgbF) -> Rgb32FImage {
         let mut out_image = Rgb32FImage::new(image_buffer.image_width as u32, image_buffer.image_height as u32);
         for x in 0..image_buffer.image_width {
             for y in 0..image_buffer.image_height {
-                let color_sample = image_buffer.get_color_sample_corrected(x, y, num_samples);
-                out_image.put_pixel(x as u32, (image_buffer.image_height - 1 - y) as u32, Rgb([color_sample.x, color_sample.y, color_sample.z]));
+                let color = pixel_color(x, y);
+                out_image.put_pixel(x as u32, (image_buffer.image_height - 1 - y) as u32, Rgb([color.x, color.y, color.z]));
             }
         }
-        let rgb8 = DynamicImage::ImageRgb32F(out_image).into_rgb8();
-        rgb8.save(self.output_file_path)?;
-        Ok(())
+        out_image
     }
-}
\ No newline at end of file
+}
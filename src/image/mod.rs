@@ -1,2 +1,5 @@
+pub mod checkpoint;
+pub mod filter;
 pub mod image_buffer;
-pub mod image_writer;
\ No newline at end of file
+pub mod image_writer;
+pub mod tone_mapping;
\ No newline at end of file
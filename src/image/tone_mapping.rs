@@ -0,0 +1,98 @@
+use serde::Deserialize;
+use crate::core::color::ColorRgbF;
+
+/// Display transform turning linear scene radiance into colors for 8 bit output formats
+#[derive(Debug, Copy, Clone, Deserialize, Default)]
+pub struct DisplaySettings {
+    /// Brightness adjustment in stops, each stop doubles the radiance before tone mapping
+    #[serde(default)]
+    pub exposure: f32,
+    #[serde(default)]
+    pub tone_mapping: ToneMapping,
+    #[serde(default)]
+    pub transfer_function: TransferFunction,
+}
+
+impl DisplaySettings {
+    /// Display encoded color in the unit interval for a linear radiance
+    pub fn apply(&self, radiance: ColorRgbF) -> ColorRgbF {
+        let exposed = radiance * 2.0f32.powf(self.exposure);
+        let tone_mapped = self.tone_mapping.apply(exposed).clamp(ColorRgbF::ZERO, ColorRgbF::ONE);
+        self.transfer_function.encode(tone_mapped)
+    }
+}
+
+/// Operators compressing the unbounded radiance range into the displayable unit interval. All of
+/// them act on each channel separately.
+#[derive(Debug, Copy, Clone, Deserialize, Default, PartialEq)]
+pub enum ToneMapping {
+    /// Radiance above one is clipped
+    #[default]
+    Clamp,
+    /// `x / (1 + x)`, which never reaches white
+    Reinhard,
+    /// Reinhard scaled so radiance of `white_point` and above maps to white
+    ExtendedReinhard { white_point: f32 },
+    /// Krzysztof Narkowicz's fit of the ACES filmic reference rendering transform
+    Aces,
+    /// John Hable's filmic curve from Uncharted 2, with radiance of 11.2 mapping to white
+    Hable,
+}
+
+impl ToneMapping {
+    pub fn apply(&self, color: ColorRgbF) -> ColorRgbF {
+        match self {
+            ToneMapping::Clamp => color,
+            ToneMapping::Reinhard => color / (ColorRgbF::ONE + color),
+            ToneMapping::ExtendedReinhard { white_point } => {
+                color * (ColorRgbF::ONE + color / (white_point * white_point)) / (ColorRgbF::ONE + color)
+            }
+            ToneMapping::Aces => {
+                (color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14)
+            }
+            ToneMapping::Hable => {
+                const WHITE_POINT: f32 = 11.2;
+                hable_curve(color) / hable_curve(ColorRgbF::splat(WHITE_POINT))
+            }
+        }
+    }
+}
+
+fn hable_curve(x: ColorRgbF) -> ColorRgbF {
+    const SHOULDER_STRENGTH: f32 = 0.15;
+    const LINEAR_STRENGTH: f32 = 0.5;
+    const LINEAR_ANGLE: f32 = 0.1;
+    const TOE_STRENGTH: f32 = 0.2;
+    const TOE_NUMERATOR: f32 = 0.02;
+    const TOE_DENOMINATOR: f32 = 0.3;
+    ((x * (SHOULDER_STRENGTH * x + LINEAR_ANGLE * LINEAR_STRENGTH) + TOE_STRENGTH * TOE_NUMERATOR)
+        / (x * (SHOULDER_STRENGTH * x + LINEAR_STRENGTH) + TOE_STRENGTH * TOE_DENOMINATOR))
+        - TOE_NUMERATOR / TOE_DENOMINATOR
+}
+
+/// Encoding from linear values to the non-linear values stored in the output file
+#[derive(Debug, Copy, Clone, Deserialize, Default, PartialEq, Eq)]
+pub enum TransferFunction {
+    /// The piecewise sRGB opto-electronic transfer function, linear near black and a 2.4 power above
+    #[default]
+    Srgb,
+    /// Square root, i.e. a gamma of 2, as earlier versions of rehnda used
+    Gamma2,
+}
+
+impl TransferFunction {
+    pub fn encode(&self, color: ColorRgbF) -> ColorRgbF {
+        match self {
+            TransferFunction::Srgb => ColorRgbF::new(srgb_encode(color.x), srgb_encode(color.y), srgb_encode(color.z)),
+            TransferFunction::Gamma2 => ColorRgbF::new(color.x.sqrt(), color.y.sqrt(), color.z.sqrt()),
+        }
+    }
+}
+
+fn srgb_encode(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        12.92 * value
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,69 @@
+//! A CPU path tracer. The `light-rehnda` binary renders scenes described by RON files, and the
+//! same renderer can be embedded by building a [Scene] from a [SceneDescription] in code:
+//!
+//! ```no_run
+//! use light_rehnda::{build_scene, render, CancellationToken, RenderOptions, SceneOptions};
+//! use light_rehnda::scene::scene_builder::load_scene_description;
+//!
+//! # fn main() -> Result<(), light_rehnda::RehndaError> {
+//! let description = load_scene_description("scenes/cornell_box.ron".as_ref())?;
+//! let scene = build_scene(&description, &SceneOptions::new(1.0))?;
+//! let mut options = RenderOptions::new(300, 300, 64);
+//! options.aggregation_config.seed = 7;
+//! let image = render(&scene, &options, &CancellationToken::new(), |progress| {
+//!     println!("{}/{} pixels", progress.finished_pixels, progress.total_pixels);
+//! });
+//! let centre = image.get_color_sample_linear(150, 150);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod acceleration;
+pub mod aggregator;
+pub mod core;
+pub mod error;
+pub mod hittable;
+pub mod image;
+pub mod light;
+pub mod material;
+pub mod sampler;
+pub mod scene;
+pub mod texture;
+pub mod util;
+
+pub use crate::aggregator::{AggregationConfig, available_threads, render_image};
+pub use crate::aggregator::render_control::{CancellationToken, RenderProgress};
+pub use crate::error::RehndaError;
+pub use crate::image::image_buffer::ImageBuffer;
+pub use crate::scene::Scene;
+pub use crate::scene::scene_builder::{build_scene, SceneOptions};
+pub use crate::scene::scene_description::SceneDescription;
+
+/// What to render a scene into and how
+#[derive(Debug, Copy, Clone)]
+pub struct RenderOptions {
+    pub image_width: usize,
+    pub image_height: usize,
+    pub num_threads: usize,
+    /// Samples per pixel, integrator, sampler and the rest of how each pixel is rendered
+    pub aggregation_config: AggregationConfig,
+}
+
+impl RenderOptions {
+    /// Renders with every available thread and the default integrator, sampler and filter
+    pub fn new(image_width: usize, image_height: usize, samples_per_pixel: usize) -> RenderOptions {
+        RenderOptions {
+            image_width,
+            image_height,
+            num_threads: available_threads(),
+            aggregation_config: AggregationConfig::new(samples_per_pixel),
+        }
+    }
+}
+
+/// Renders `scene` from scratch, calling `on_progress` from the render threads as it goes. Use
+/// [render_image] to resume from earlier samples or be handed progressive checkpoints.
+pub fn render(scene: &Scene, options: &RenderOptions, cancellation: &CancellationToken, on_progress: impl Fn(&RenderProgress) + Sync) -> ImageBuffer {
+    let framebuffer = ImageBuffer::new(options.image_width, options.image_height);
+    render_image(&options.aggregation_config, scene, framebuffer, options.num_threads, cancellation, on_progress, |_| {})
+}
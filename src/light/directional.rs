@@ -0,0 +1,30 @@
+use crate::core::color::ColorRgbF;
+use crate::core::rehnda_math::{Point3f, Vec3Ext, Vec3f};
+use crate::light::{IncidentLight, PunctualLight};
+
+/// Parallel light from infinitely far away, casting perfectly sharp shadows
+pub struct DirectionalLight {
+    /// Unit vector towards the light, opposite to the direction the light travels
+    to_light: Vec3f,
+    irradiance: ColorRgbF,
+}
+
+impl DirectionalLight {
+    /// Light travelling along `direction`, giving `irradiance` to surfaces facing it
+    pub fn new(direction: &Vec3f, irradiance: ColorRgbF) -> DirectionalLight {
+        DirectionalLight {
+            to_light: -direction.unit_vector(),
+            irradiance,
+        }
+    }
+}
+
+impl PunctualLight for DirectionalLight {
+    fn sample_incident(&self, _point: &Point3f) -> Option<IncidentLight> {
+        Some(IncidentLight {
+            direction: self.to_light,
+            distance: f32::MAX,
+            irradiance: self.irradiance,
+        })
+    }
+}
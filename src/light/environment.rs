@@ -0,0 +1,128 @@
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use glam::{Mat3A, Vec2};
+use image::codecs::hdr::HdrDecoder;
+use image::ImageFormat;
+use crate::core::color::{ColorRgbF, luminance};
+use crate::core::rehnda_math::Vec3f;
+use crate::error::RehndaError;
+use crate::light::EnvironmentLight;
+use crate::sampler::distribution::Distribution2D;
+
+/// Light arriving from every direction at infinity, stored as a latitude-longitude image: columns
+/// go around the up (y) axis with the middle column looking down -z, rows go from straight up at
+/// the top to straight down at the bottom.
+///
+/// Directions are importance sampled in proportion to the luminance of the texels, weighted by the
+/// solid angle they cover, so small bright features like the sun are found by shadow rays.
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    /// Radiance of each texel row by row from the top, scaled by the intensity
+    texels: Vec<ColorRgbF>,
+    world_to_map: Mat3A,
+    map_to_world: Mat3A,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    /// `rotation_degrees` turns the map about the up axis, `intensity` scales its radiance. The
+    /// map has to be at least one texel wide and high.
+    pub fn new(width: usize, height: usize, texels: Vec<ColorRgbF>, rotation_degrees: f32, intensity: f32) -> EnvironmentMap {
+        let texels: Vec<ColorRgbF> = texels.into_iter().map(|texel| intensity * texel).collect();
+        let weights: Vec<f32> = texels.iter().enumerate()
+            .map(|(index, texel)| {
+                let theta = PI * ((index / width) as f32 + 0.5) / height as f32;
+                luminance(texel) * theta.sin()
+            })
+            .collect();
+        let map_to_world = Mat3A::from_rotation_y(rotation_degrees.to_radians());
+        EnvironmentMap {
+            width,
+            height,
+            texels,
+            world_to_map: map_to_world.transpose(),
+            map_to_world,
+            distribution: Distribution2D::new(&weights, width, height),
+        }
+    }
+
+    /// Map of the radiance arriving from the middle of each texel, e.g. of an analytic sky
+    pub fn from_fn(width: usize, height: usize, radiance: impl Fn(&Vec3f) -> ColorRgbF) -> EnvironmentMap {
+        let texels = (0..width * height)
+            .map(|index| {
+                let uv = Vec2::new(((index % width) as f32 + 0.5) / width as f32, ((index / width) as f32 + 0.5) / height as f32);
+                radiance(&uv_to_direction(uv))
+            })
+            .collect();
+        EnvironmentMap::new(width, height, texels, 0.0, 1.0)
+    }
+
+    /// Loads a linear Radiance (`.hdr`) or OpenEXR (`.exr`) image
+    pub fn load(path: &Path, rotation_degrees: f32, intensity: f32) -> Result<EnvironmentMap, RehndaError> {
+        let to_error = |source| RehndaError::EnvironmentMap { path: path.to_path_buf(), source };
+        let (width, height, texels) = if ImageFormat::from_path(path).ok() == Some(ImageFormat::Hdr) {
+            // `image::open` tone maps Radiance files down to 8 bits, so they're decoded directly
+            let file = File::open(path).map_err(|source| RehndaError::Read { path: path.to_path_buf(), source })?;
+            let decoder = HdrDecoder::new(BufReader::new(file)).map_err(to_error)?;
+            let metadata = decoder.metadata();
+            let pixels = decoder.read_image_hdr().map_err(to_error)?;
+            (metadata.width, metadata.height, pixels.iter().map(|pixel| ColorRgbF::new(pixel.0[0], pixel.0[1], pixel.0[2])).collect())
+        } else {
+            let image = image::open(path).map_err(to_error)?.into_rgb32f();
+            (image.width(), image.height(), image.pixels().map(|pixel| ColorRgbF::new(pixel.0[0], pixel.0[1], pixel.0[2])).collect())
+        };
+        if width == 0 || height == 0 {
+            return Err(RehndaError::InvalidScene(format!("environment map {} has no pixels, it is {}x{}", path.display(), width, height)));
+        }
+        Ok(EnvironmentMap::new(width as usize, height as usize, texels, rotation_degrees, intensity))
+    }
+
+    /// Luminance arriving from all directions, integrated over the sphere
+    pub fn total_luminance(&self) -> f32 {
+        // the distribution's integral is over the unit square, which covers 2 pi by pi radians
+        2.0 * PI * PI * self.distribution.integral()
+    }
+
+    fn direction_to_uv(&self, direction: &Vec3f) -> Vec2 {
+        let local = (self.world_to_map * *direction).normalize();
+        let theta = local.y.clamp(-1.0, 1.0).acos();
+        let phi = local.x.atan2(-local.z);
+        Vec2::new(0.5 + phi / (2.0 * PI), theta / PI)
+    }
+}
+
+impl EnvironmentLight for EnvironmentMap {
+    /// Radiance arriving from `direction`, looked up in the nearest texel so it matches the
+    /// piecewise constant sampling density
+    fn radiance(&self, direction: &Vec3f) -> ColorRgbF {
+        let uv = self.direction_to_uv(direction);
+        let x = ((uv.x * self.width as f32) as usize).min(self.width - 1);
+        let y = ((uv.y * self.height as f32) as usize).min(self.height - 1);
+        self.texels[x + y * self.width]
+    }
+
+    fn sample_direction(&self, u: Vec2) -> Vec3f {
+        let (uv, _) = self.distribution.sample_continuous(u);
+        self.map_to_world * uv_to_direction(uv)
+    }
+
+    fn pdf(&self, direction: &Vec3f) -> f32 {
+        let uv = self.direction_to_uv(direction);
+        let sin_theta = (PI * uv.y).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        // the map's unit square covers 2 pi by pi radians, shrunk by sin theta towards the poles
+        self.distribution.pdf(uv) / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+/// Direction in the map's frame at `uv` within the map
+fn uv_to_direction(uv: Vec2) -> Vec3f {
+    let theta = PI * uv.y;
+    let phi = 2.0 * PI * (uv.x - 0.5);
+    Vec3f::new(theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos())
+}
@@ -0,0 +1,33 @@
+use glam::Vec2;
+use crate::core::color::ColorRgbF;
+use crate::core::rehnda_math::{Point3f, Vec3f};
+
+/// Light arriving from infinitely far away in every direction, seen by rays that escape the world
+/// and sampled directly by integrators using next event estimation
+pub trait EnvironmentLight: Send + Sync {
+    /// Radiance arriving from `direction`
+    fn radiance(&self, direction: &Vec3f) -> ColorRgbF;
+
+    /// Direction towards the light, chosen roughly in proportion to the radiance arriving from it
+    fn sample_direction(&self, u: Vec2) -> Vec3f;
+
+    /// Solid angle density of [EnvironmentLight::sample_direction] choosing `direction`
+    fn pdf(&self, direction: &Vec3f) -> f32;
+}
+/// Light from a single point or from a single direction, like a bulb or the sun seen from far
+/// away. Rays can never hit it, so it's only found by integrators sampling lights with shadow rays.
+pub trait PunctualLight: Send + Sync {
+    /// Light arriving at `point`, or `None` if none of it reaches the point
+    fn sample_incident(&self, point: &Point3f) -> Option<IncidentLight>;
+}
+
+/// Light arriving at a point from a [PunctualLight]
+#[derive(Debug, Copy, Clone)]
+pub struct IncidentLight {
+    /// Unit vector from the point towards the light
+    pub direction: Vec3f,
+    /// Distance to the light, `f32::MAX` for lights infinitely far away
+    pub distance: f32,
+    /// Irradiance on a surface at the point facing the light
+    pub irradiance: ColorRgbF,
+}
@@ -0,0 +1,8 @@
+mod light;
+pub use light::*;
+
+pub mod directional;
+pub mod environment;
+pub mod point;
+pub mod sky;
+pub mod spot;
@@ -0,0 +1,26 @@
+use crate::core::color::ColorRgbF;
+use crate::core::rehnda_math::Point3f;
+use crate::light::{IncidentLight, PunctualLight};
+
+/// Infinitely small light shining equally in every direction
+pub struct PointLight {
+    pub position: Point3f,
+    /// Power per steradian, the irradiance it gives a surface one unit away
+    pub intensity: ColorRgbF,
+}
+
+impl PunctualLight for PointLight {
+    fn sample_incident(&self, point: &Point3f) -> Option<IncidentLight> {
+        let to_light = self.position - *point;
+        let distance_squared = to_light.length_squared();
+        if distance_squared <= 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        Some(IncidentLight {
+            direction: to_light / distance,
+            distance,
+            irradiance: self.intensity / distance_squared,
+        })
+    }
+}
@@ -0,0 +1,197 @@
+use std::f32::consts::PI;
+use glam::Vec2;
+use crate::core::color::{ColorRgbF, luminance};
+use crate::core::onb::Onb;
+use crate::core::rehnda_math::Vec3f;
+use crate::light::environment::EnvironmentMap;
+use crate::light::EnvironmentLight;
+
+/// Resolution the sky is baked at for importance sampling, the sky itself is smooth
+const SKY_MAP_WIDTH: usize = 256;
+const SKY_MAP_HEIGHT: usize = 128;
+
+/// Render radiance of one kcd/m² of the sky model, chosen so a white diffuse surface in the
+/// midday sun comes out at around one
+const SKY_RADIANCE_SCALE: f32 = 0.03;
+
+/// Luminance of the sun's disc above the atmosphere in kcd/m²
+const SUN_LUMINANCE: f32 = 2.0e6;
+
+/// Angular diameter of the real sun in degrees
+pub const SUN_ANGULAR_DIAMETER: f32 = 0.53;
+
+/// Clear sky after Preetham, Shirley and Smits' analytic daylight model, lit by a sun whose disc
+/// has the colour of sunlight passing through the same atmosphere.
+///
+/// The sky is sampled from a baked environment map, the sun's disc uniformly, choosing between
+/// them in proportion to the light they give.
+pub struct Sky {
+    sky_map: EnvironmentMap,
+    sun_direction: Vec3f,
+    sun_basis: Onb,
+    cos_sun_radius: f32,
+    sun_radiance: ColorRgbF,
+    sun_probability: f32,
+}
+
+impl Sky {
+    /// The sun is `sun_elevation` degrees above the horizon (0 to 90), turned `sun_azimuth`
+    /// degrees about the up (y) axis from -z towards +x. `turbidity` is the haziness of the
+    /// atmosphere from 2 (clear) to 10 (hazy). A larger `sun_angular_diameter` softens shadows
+    /// while keeping the light from the sun the same, `intensity` scales the sky and sun.
+    pub fn new(sun_elevation: f32, sun_azimuth: f32, turbidity: f32, sun_angular_diameter: f32, intensity: f32) -> Sky {
+        let elevation = sun_elevation.to_radians();
+        let azimuth = sun_azimuth.to_radians();
+        let sun_direction = Vec3f::new(elevation.cos() * azimuth.sin(), elevation.sin(), -elevation.cos() * azimuth.cos());
+
+        let model = PreethamSky::new(&sun_direction, turbidity);
+        let sky_map = EnvironmentMap::from_fn(SKY_MAP_WIDTH, SKY_MAP_HEIGHT, |direction| intensity * SKY_RADIANCE_SCALE * model.radiance(direction));
+
+        let cos_sun_radius = (0.5 * sun_angular_diameter.to_radians()).cos();
+        let sun_solid_angle = cone_solid_angle(cos_sun_radius);
+        let real_sun_solid_angle = cone_solid_angle((0.5 * SUN_ANGULAR_DIAMETER.to_radians()).cos());
+        let sun_radiance = intensity * SKY_RADIANCE_SCALE * SUN_LUMINANCE * real_sun_solid_angle / sun_solid_angle
+            * sun_transmittance(PI / 2.0 - elevation, turbidity);
+
+        let sun_power = luminance(&sun_radiance) * sun_solid_angle;
+        let sun_probability = if sun_power > 0.0 { sun_power / (sun_power + sky_map.total_luminance()) } else { 0.0 };
+        Sky {
+            sky_map,
+            sun_direction,
+            sun_basis: Onb::build_from_w(&sun_direction),
+            cos_sun_radius,
+            sun_radiance,
+            sun_probability,
+        }
+    }
+}
+
+impl EnvironmentLight for Sky {
+    fn radiance(&self, direction: &Vec3f) -> ColorRgbF {
+        let sky = self.sky_map.radiance(direction);
+        if direction.normalize().dot(self.sun_direction) >= self.cos_sun_radius {
+            sky + self.sun_radiance
+        } else {
+            sky
+        }
+    }
+
+    fn sample_direction(&self, u: Vec2) -> Vec3f {
+        // the first dimension picks the sun or the sky and is stretched back over the unit interval
+        if u.x < self.sun_probability {
+            let u = Vec2::new(u.x / self.sun_probability, u.y);
+            let cos_theta = 1.0 - u.x * (1.0 - self.cos_sun_radius);
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = 2.0 * PI * u.y;
+            self.sun_basis.local(&Vec3f::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta))
+        } else {
+            let u = Vec2::new((u.x - self.sun_probability) / (1.0 - self.sun_probability), u.y);
+            self.sky_map.sample_direction(u)
+        }
+    }
+
+    fn pdf(&self, direction: &Vec3f) -> f32 {
+        let sun_pdf = if direction.normalize().dot(self.sun_direction) >= self.cos_sun_radius {
+            1.0 / cone_solid_angle(self.cos_sun_radius)
+        } else {
+            0.0
+        };
+        self.sun_probability * sun_pdf + (1.0 - self.sun_probability) * self.sky_map.pdf(direction)
+    }
+}
+
+fn cone_solid_angle(cos_half_angle: f32) -> f32 {
+    2.0 * PI * (1.0 - cos_half_angle)
+}
+
+/// Fraction of the sun's red, green and blue light reaching the ground when it's `zenith_angle`
+/// radians from straight up, attenuated by Rayleigh scattering off the air and Ångström's aerosol
+/// scattering for the turbidity
+fn sun_transmittance(zenith_angle: f32, turbidity: f32) -> ColorRgbF {
+    // relative optical air mass (Kasten and Young), about 38 at the horizon
+    let air_mass = 1.0 / (zenith_angle.cos() + 0.50572 * (96.07995 - zenith_angle.to_degrees()).powf(-1.6364));
+    let beta = 0.04608 * turbidity - 0.04586;
+    let transmittance = |wavelength_micrometres: f32| {
+        let rayleigh_depth = 0.008735 * wavelength_micrometres.powf(-4.08);
+        let aerosol_depth = beta * wavelength_micrometres.powf(-1.3);
+        (-air_mass * (rayleigh_depth + aerosol_depth)).exp()
+    };
+    ColorRgbF::new(transmittance(0.68), transmittance(0.55), transmittance(0.44))
+}
+
+/// Preetham sky luminance and chromaticity in kcd/m², from Perez et al.'s distribution fitted to
+/// the sun position and turbidity
+struct PreethamSky {
+    sun_direction: Vec3f,
+    /// Perez coefficients A to E for Y, x and y
+    coefficients: [[f32; 5]; 3],
+    /// Y, x and y at the zenith divided by the Perez distribution there, which scales the
+    /// distribution to the zenith values
+    zenith_scales: [f32; 3],
+}
+
+impl PreethamSky {
+    fn new(sun_direction: &Vec3f, turbidity: f32) -> PreethamSky {
+        let t = turbidity;
+        let coefficients = [
+            [0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703],
+            [-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452],
+            [-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529],
+        ];
+
+        let theta_s = sun_direction.y.clamp(0.0, 1.0).acos();
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let theta_s_powers = [theta_s * theta_s * theta_s, theta_s * theta_s, theta_s, 1.0];
+        let chromaticity = |matrix: [[f32; 4]; 3]| -> f32 {
+            let [t2_row, t_row, constant_row] = matrix.map(|row| row.iter().zip(theta_s_powers).map(|(c, p)| c * p).sum::<f32>());
+            t * t * t2_row + t * t_row + constant_row
+        };
+        let zenith_x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let zenith_values = [zenith_luminance, zenith_x, zenith_y];
+        let zenith_scales = [0, 1, 2].map(|channel| zenith_values[channel] / perez(&coefficients[channel], 1.0, theta_s));
+        PreethamSky {
+            sun_direction: *sun_direction,
+            coefficients,
+            zenith_scales,
+        }
+    }
+
+    /// Linear Rec. 709 radiance from `direction`, continuing the sky at the horizon below it
+    fn radiance(&self, direction: &Vec3f) -> ColorRgbF {
+        let direction = direction.normalize();
+        let cos_theta = direction.y.max(0.0);
+        let gamma = direction.dot(self.sun_direction).clamp(-1.0, 1.0).acos();
+        let [luminance, x, y] = [0, 1, 2].map(|channel| self.zenith_scales[channel] * perez(&self.coefficients[channel], cos_theta, gamma));
+        if luminance <= 0.0 || y <= 0.0 {
+            return ColorRgbF::ZERO;
+        }
+
+        let cie_x = x / y * luminance;
+        let cie_z = (1.0 - x - y) / y * luminance;
+        ColorRgbF::new(
+            3.2406 * cie_x - 1.5372 * luminance - 0.4986 * cie_z,
+            -0.9689 * cie_x + 1.8758 * luminance + 0.0415 * cie_z,
+            0.0557 * cie_x - 0.2040 * luminance + 1.0570 * cie_z,
+        ).max(ColorRgbF::ZERO)
+    }
+}
+
+/// Perez et al.'s sky distribution for a direction at an angle with cosine `cos_theta` to the
+/// zenith and `gamma` radians from the sun
+fn perez(coefficients: &[f32; 5], cos_theta: f32, gamma: f32) -> f32 {
+    let [a, b, c, d, e] = *coefficients;
+    // the horizon is approached rather than reached, where b / cos_theta would divide by zero
+    let cos_theta = cos_theta.max(0.01);
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos() * gamma.cos())
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use crate::core::color::ColorRgbF;
+use crate::core::rehnda_math::{Point3f, Vec3Ext, Vec3f};
+use crate::light::{IncidentLight, PunctualLight};
+use crate::texture::{Texture, Uv};
+
+/// Point light shining in a cone, fading out smoothly over the outer edge of the cone
+pub struct SpotLight {
+    position: Point3f,
+    /// Unit vector down the middle of the cone
+    axis: Vec3f,
+    intensity: ColorRgbF,
+    /// Cosines of the angles from the axis where the light starts fading and where it's gone
+    cos_falloff_start: f32,
+    cos_cone_angle: f32,
+    projection: Option<Projection>,
+}
+
+/// Image projected by a spot light like a slide projector, stretched over the square the cone fits in
+struct Projection {
+    texture: Arc<dyn Texture>,
+    /// Unit vectors towards the right and top of the image, perpendicular to the axis
+    right: Vec3f,
+    up: Vec3f,
+    tan_cone_angle: f32,
+}
+
+impl SpotLight {
+    /// Spot at `position` shining towards `look_at`, lit up to `cone_angle` degrees from its axis
+    /// with the last `falloff_angle` degrees of that fading out
+    pub fn new(position: Point3f, look_at: &Point3f, intensity: ColorRgbF, cone_angle: f32, falloff_angle: f32) -> SpotLight {
+        SpotLight {
+            position,
+            axis: (*look_at - position).unit_vector(),
+            intensity,
+            cos_falloff_start: (cone_angle - falloff_angle).to_radians().cos(),
+            cos_cone_angle: cone_angle.to_radians().cos(),
+            projection: None,
+        }
+    }
+
+    /// Colours the light with `texture`, with `up` towards the top of the image. The cone angle
+    /// has to be below 90 degrees and `up` not along the axis.
+    pub fn with_projection(self, texture: Arc<dyn Texture>, up: &Vec3f) -> SpotLight {
+        let right = self.axis.cross(*up).unit_vector();
+        let projection = Projection {
+            texture,
+            right,
+            up: right.cross(self.axis),
+            tan_cone_angle: self.cos_cone_angle.acos().tan(),
+        };
+        SpotLight { projection: Some(projection), ..self }
+    }
+
+    /// Fraction of the intensity shining along `direction`, a unit vector from the light
+    fn falloff(&self, direction: &Vec3f) -> f32 {
+        let cos_theta = direction.dot(self.axis);
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+        if cos_theta <= self.cos_cone_angle {
+            return 0.0;
+        }
+        let t = (cos_theta - self.cos_cone_angle) / (self.cos_falloff_start - self.cos_cone_angle);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl PunctualLight for SpotLight {
+    fn sample_incident(&self, point: &Point3f) -> Option<IncidentLight> {
+        let to_light = self.position - *point;
+        let distance_squared = to_light.length_squared();
+        if distance_squared <= 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        let direction = to_light / distance;
+        let falloff = self.falloff(&-direction);
+        if falloff <= 0.0 {
+            return None;
+        }
+
+        let mut intensity = falloff * self.intensity;
+        if let Some(projection) = &self.projection {
+            // where the direction crosses the image held one unit in front of the light
+            let from_light = -direction / (-direction).dot(self.axis);
+            let scale = 0.5 / projection.tan_cone_angle;
+            let uv = Uv::new(0.5 + scale * from_light.dot(projection.right), 0.5 + scale * from_light.dot(projection.up));
+            intensity *= projection.texture.sample(&uv, point);
+        }
+        Some(IncidentLight {
+            direction,
+            distance,
+            irradiance: intensity / distance_squared,
+        })
+    }
+}
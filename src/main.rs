@@ -1,81 +1,248 @@
+use std::fmt::Write;
+use std::io::ErrorKind;
 use std::path::Path;
-use std::thread;
-use std::thread::JoinHandle;
+use std::process::{self, ExitCode};
 use std::time::Instant;
 
-use clap::Parser;
-use log::info;
+use clap::{Args, Parser, Subcommand};
+use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+use log::{error, info, warn};
 use simplelog::*;
 
-use crate::aggregator::{AggregationConfig, sample_pixels};
-use crate::hittable::Hittable;
-use crate::image::image_buffer::ImageBuffer;
-use crate::image::image_writer::ImageFileWriter;
-use crate::scene::Scene;
-use crate::scene::scene_builder::load_scene;
-use crate::scene::settings::RehndaSettings;
-
-mod acceleration;
-mod aggregator;
-mod core;
-mod hittable;
-mod image;
-mod material;
-mod scene;
-mod texture;
-mod util;
+use light_rehnda::{CancellationToken, ImageBuffer, RehndaError, render_image, RenderProgress};
+use light_rehnda::aggregator::integrator::Integrator;
+use light_rehnda::aggregator::progressive::ProgressiveRendering;
+use light_rehnda::image::checkpoint::Checkpoint;
+use light_rehnda::image::image_writer::ImageFileWriter;
+use light_rehnda::scene::bvh_benchmark::run_bvh_benchmark;
+use light_rehnda::scene::scene_builder::load_scene;
+use light_rehnda::scene::scene_info::print_scene_info;
+use light_rehnda::scene::settings::RehndaSettings;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-struct Args {
-    #[arg(short, long)]
-    output_file: Option<String>,
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Render the scene
+    Render {
+        #[command(flatten)]
+        settings: SettingsArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+        /// Carry on rendering from a checkpoint saved with the same settings
+        #[arg(long)]
+        resume: Option<String>,
+    },
+    /// Add up checkpoints rendered with the same settings into one image
+    Merge {
+        #[command(flatten)]
+        settings: SettingsArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+        #[arg(required = true)]
+        checkpoints: Vec<String>,
+    },
+    /// Check the settings and scene load and build, without rendering
+    Validate {
+        #[command(flatten)]
+        settings: SettingsArgs,
+    },
+    /// Print statistics about the scene
+    Info {
+        #[command(flatten)]
+        settings: SettingsArgs,
+    },
+    /// Time the BVH layouts against each other on the scene
+    Bench {
+        #[command(flatten)]
+        settings: SettingsArgs,
+    },
+}
+
+#[derive(Args, Debug)]
+struct SettingsArgs {
     #[arg(short, long)]
     settings_file: String,
+    /// Override a setting, e.g. `--set samples=64` or `--set camera_settings.aperture=0.1`. Nested
+    /// fields are separated by dots, values are written in RON. Can be repeated.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    overrides: Vec<String>,
 }
 
-fn main() {
+impl SettingsArgs {
+    fn load(&self) -> Result<RehndaSettings, RehndaError> {
+        RehndaSettings::from_file(Path::new(&self.settings_file), &self.overrides)
+    }
+}
+
+#[derive(Args, Debug)]
+struct OutputArgs {
+    #[arg(short, long)]
+    output_file: Option<String>,
+    /// Where to save the raw sample sums, overriding `checkpoint_file` in the settings
+    #[arg(long)]
+    checkpoint_file: Option<String>,
+}
+
+/// Where a render's image and checkpoint are written
+struct Output<'a> {
+    image_writer: ImageFileWriter<'a>,
+    checkpoint_path: Option<&'a Path>,
+}
+
+impl<'a> Output<'a> {
+    fn new(output_args: &'a OutputArgs, settings: &'a RehndaSettings) -> Output<'a> {
+        let output_file_str = output_args.output_file.as_ref().or(settings.output_file.as_ref());
+        Output {
+            image_writer: ImageFileWriter {
+                output_file_path: output_file_str.map_or(Path::new("out.ppm"), Path::new),
+                display_settings: &settings.display_settings,
+            },
+            checkpoint_path: output_args.checkpoint_file.as_ref().or(settings.checkpoint_file.as_ref()).map(Path::new),
+        }
+    }
+
+    fn save(&self, checkpoint: &Checkpoint) -> Result<(), RehndaError> {
+        let image_path = self.image_writer.output_file_path;
+        self.image_writer.write_to_file(&checkpoint.image_buffer)
+            .map_err(|source| RehndaError::WriteImage { path: image_path.to_path_buf(), source })?;
+        if let Some(checkpoint_path) = self.checkpoint_path {
+            checkpoint.write_to_file(checkpoint_path)
+                .map_err(|source| RehndaError::Write { path: checkpoint_path.to_path_buf(), source })?;
+        }
+        Ok(())
+    }
+}
+
+fn main() -> ExitCode {
     TermLogger::init(LevelFilter::Info, Config::default(), TerminalMode::Mixed, ColorChoice::Auto).unwrap();
-    let args = Args::parse();
-    let settings = RehndaSettings::from_file(Path::new(&args.settings_file));
-    info!("Rendering at resolution: {}x{}", settings.image_width, settings.image_height());
-    let scene = load_scene(&settings);
-
-    let aggregation_config = AggregationConfig {
-        samples_per_pixel: settings.num_samples_per_thread(),
-        max_sample_depth: settings.max_depth,
-    };
-    info!("Rendering using {} threads", settings.num_threads());
-    let mut render_thread_handles: Vec<JoinHandle<ImageBuffer>> = Vec::new();
-    for _i in 1..settings.num_threads() {
-        render_thread_handles.push(spawn_render_thread(aggregation_config, scene.clone(), settings.image_width, settings.image_height()));
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            error!("{}", error);
+            ExitCode::from(error.exit_code())
+        }
     }
-    let mut main_buffer = ImageBuffer::new(settings.image_width, settings.image_height());
+}
 
-    let render_start = Instant::now();
-    info!("Main thread starting rendering");
-    sample_pixels(&aggregation_config, &scene, &mut main_buffer, true);
-    info!("Main thread done. Main thread took: {:?}", render_start.elapsed());
+fn run(command: Command) -> Result<(), RehndaError> {
+    match command {
+        Command::Render { settings, output, resume } => {
+            let settings = settings.load()?;
+            render(&settings, &Output::new(&output, &settings), resume.as_deref())
+        }
+        Command::Merge { settings, output, checkpoints } => {
+            let settings = settings.load()?;
+            merge(&settings, &Output::new(&output, &settings), &checkpoints)
+        }
+        Command::Validate { settings: settings_args } => {
+            let settings = settings_args.load()?;
+            load_scene(&settings)?;
+            info!("{} and {} are valid", settings_args.settings_file, settings.scene_file);
+            Ok(())
+        }
+        Command::Info { settings } => print_scene_info(&settings.load()?),
+        Command::Bench { settings } => run_bvh_benchmark(&settings.load()?),
+    }
+}
+
+fn render(settings: &RehndaSettings, output: &Output, resume: Option<&str>) -> Result<(), RehndaError> {
+    let settings_hash = settings.render_hash()?;
+    let (framebuffer, seed, merged_seeds) = match resume {
+        Some(resume_path) => {
+            let checkpoint = read_checkpoint(Path::new(resume_path), settings_hash)?;
+            info!("Resuming from {} with {} samples taken", resume_path, checkpoint.image_buffer.total_sample_count());
+            (checkpoint.image_buffer, checkpoint.seed, checkpoint.merged_seeds)
+        }
+        None => {
+            let (image_width, image_height) = settings.resolution();
+            (ImageBuffer::new(image_width, image_height), settings.seed, Vec::new())
+        }
+    };
 
-    render_thread_handles.into_iter().for_each(|x| main_buffer.add_buffer(&x.join().unwrap()));
+    let (image_width, image_height) = settings.resolution();
+    info!("Rendering at resolution: {}x{}", image_width, image_height);
+    let scene = load_scene(settings)?;
+    if settings.integrator == Integrator::PathTracer && !scene.punctual_lights.is_empty() {
+        warn!("The PathTracer integrator can't find point, spot or directional lights, use NextEventEstimation or MultipleImportanceSampling to see them");
+    }
+
+    let mut aggregation_config = settings.aggregation_config();
+    // continuing with the checkpoint's seed keeps every sample's random numbers the same as in an
+    // uninterrupted render
+    aggregation_config.seed = seed;
 
-    let render_duration = render_start.elapsed();
-    info!("All threads done. Took {:?}", render_duration);
+    // the first Ctrl-C stops the render and still saves it, a second one quits straight away
+    let cancellation = CancellationToken::new();
+    let handler_cancellation = cancellation.clone();
+    ctrlc::set_handler(move || {
+        if handler_cancellation.cancel() {
+            process::exit(130);
+        }
+        warn!("Stopping after the tiles being rendered, press Ctrl-C again to quit without saving");
+    }).expect("Error setting Ctrl-C handler");
 
-    let output_file_str = args.output_file.as_ref().or(settings.output_file.as_ref());
-    let out_path = output_file_str.map_or(Path::new("out.ppm"), Path::new);
-    let image_writer = ImageFileWriter {
-        output_file_path: out_path,
+    info!("Rendering using {} threads", settings.num_threads());
+    let render_start = Instant::now();
+    let progress_bar = ProgressBar::new(0);
+    progress_bar.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} pixels {msg} ({eta})")
+        .unwrap()
+        .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
+        .progress_chars("#>-"));
+    let on_progress = |progress: &RenderProgress| {
+        progress_bar.set_length(progress.total_pixels as u64);
+        progress_bar.set_position(progress.finished_pixels as u64);
+        if settings.progressive.is_some() {
+            let sample_limit = ProgressiveRendering::pass_sample_limit(progress.pass).min(settings.num_samples);
+            progress_bar.set_message(format!("(pass {}, up to {} samples)", progress.pass + 1, sample_limit));
+        }
     };
-    // image_writer.write_image_buffer_to_ppm(&main_buffer, settings.num_samples_per_thread() * settings.num_threads()).unwrap();
-    image_writer.write_to_file(&main_buffer, settings.num_samples_per_thread() * settings.num_threads()).unwrap();
+    let main_buffer = render_image(&aggregation_config, &scene, framebuffer, settings.num_threads(), &cancellation, on_progress, |image_buffer| {
+        // a checkpoint that can't be written isn't worth abandoning the render for, the next one may succeed
+        progress_bar.suspend(|| match output.save(&Checkpoint { settings_hash, seed, merged_seeds: merged_seeds.clone(), image_buffer: image_buffer.clone() }) {
+            Ok(()) => info!("Wrote checkpoint to {} after {:?}", output.image_writer.output_file_path.display(), render_start.elapsed()),
+            Err(error) => warn!("{}", error),
+        });
+    });
+    progress_bar.finish();
+    info!("All threads done. Took {:?}", render_start.elapsed());
+
+    output.save(&Checkpoint { settings_hash, seed, merged_seeds, image_buffer: main_buffer })?;
     info!("Done!");
+    Ok(())
 }
 
-fn spawn_render_thread(aggregation_config: AggregationConfig, scene: Scene, image_width: usize, image_height: usize) -> JoinHandle<ImageBuffer> {
-    thread::spawn(move || {
-        let mut img_buffer = ImageBuffer::new(image_width, image_height);
-        sample_pixels(&aggregation_config, &scene, &mut img_buffer, false);
-        img_buffer
-    })
+fn merge(settings: &RehndaSettings, output: &Output, checkpoint_paths: &[String]) -> Result<(), RehndaError> {
+    let settings_hash = settings.render_hash()?;
+    let (first_path, other_paths) = checkpoint_paths.split_first().expect("clap requires at least one checkpoint");
+    let mut merged = read_checkpoint(Path::new(first_path), settings_hash)?;
+    for path in other_paths {
+        let checkpoint = read_checkpoint(Path::new(path), settings_hash)?;
+        merged.merge(&checkpoint)
+            .map_err(|error| RehndaError::InvalidCheckpoint { path: path.into(), message: error.to_string() })?;
+    }
+    output.save(&merged)?;
+    info!("Merged {} checkpoints into {}", checkpoint_paths.len(), output.image_writer.output_file_path.display());
+    Ok(())
+}
+
+/// Reads a checkpoint, which has to have been rendered with settings matching `settings_hash`
+fn read_checkpoint(path: &Path, settings_hash: u64) -> Result<Checkpoint, RehndaError> {
+    let checkpoint = Checkpoint::read_from_file(path).map_err(|source| match source.kind() {
+        ErrorKind::InvalidData => RehndaError::InvalidCheckpoint { path: path.to_path_buf(), message: source.to_string() },
+        ErrorKind::UnexpectedEof => RehndaError::InvalidCheckpoint { path: path.to_path_buf(), message: "is too short to be a checkpoint".to_string() },
+        _ => RehndaError::Read { path: path.to_path_buf(), source },
+    })?;
+    if checkpoint.settings_hash != settings_hash {
+        return Err(RehndaError::InvalidCheckpoint {
+            path: path.to_path_buf(),
+            message: "was rendered with different settings or a different scene".to_string(),
+        });
+    }
+    Ok(checkpoint)
 }
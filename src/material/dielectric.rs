@@ -1,8 +1,9 @@
 use crate::core::color::ColorRgbF;
 use crate::core::ray::Ray;
-use crate::core::rehnda_math::{random, Vec3Ext};
+use crate::core::rehnda_math::Vec3Ext;
 use crate::hittable::HitResult;
 use crate::material::{Material, Scatter};
+use crate::sampler::PixelSampler;
 
 #[derive(Debug, Copy, Clone)]
 pub struct DielectricMaterial {
@@ -10,7 +11,7 @@ pub struct DielectricMaterial {
 }
 
 impl Material for DielectricMaterial {
-    fn scatter(&self, ray_in: &Ray, hit_result: &HitResult) -> Option<Scatter> {
+    fn scatter(&self, ray_in: &Ray, hit_result: &HitResult, sampler: &mut PixelSampler) -> Option<Scatter> {
         let attenuation = ColorRgbF::splat(1.0);
 
         let refraction_ratio = if hit_result.front_face {
@@ -24,7 +25,7 @@ impl Material for DielectricMaterial {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let scatter_direction = if cannot_refract || reflectance(cos_theta, refraction_ratio) > random() {
+        let scatter_direction = if cannot_refract || reflectance(cos_theta, refraction_ratio) > sampler.get_1d() {
             unit_dir.reflect(hit_result.normal)
         } else {
             unit_dir.refract(hit_result.normal, refraction_ratio)
@@ -38,6 +39,7 @@ impl Material for DielectricMaterial {
         Some(Scatter{
             scattered_ray,
             attenuation,
+            pdf: 0.0,
         })
     }
 }
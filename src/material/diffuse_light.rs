@@ -5,6 +5,7 @@ use crate::core::ray::Ray;
 use crate::core::rehnda_math::Point3f;
 use crate::hittable::HitResult;
 use crate::material::{Material, Scatter};
+use crate::sampler::PixelSampler;
 use crate::texture::solid::SolidTexture;
 use crate::texture::{Texture, Uv};
 
@@ -18,6 +19,12 @@ impl DiffuseLight {
             texture: Arc::new(SolidTexture::new(color.x, color.y, color.z)),
         }
     }
+
+    pub fn new_with_texture(texture: Arc<dyn Texture>) -> DiffuseLight {
+        DiffuseLight {
+            texture,
+        }
+    }
 }
 
 impl Debug for DiffuseLight {
@@ -27,11 +34,15 @@ impl Debug for DiffuseLight {
 }
 
 impl Material for DiffuseLight {
-    fn scatter(&self, ray_in: &Ray, hit_result: &HitResult) -> Option<Scatter> {
+    fn scatter(&self, _ray_in: &Ray, _hit_result: &HitResult, _sampler: &mut PixelSampler) -> Option<Scatter> {
         None
     }
 
     fn emitted(&self, uv: &Uv, point: &Point3f) -> ColorRgbF {
         self.texture.sample(uv, point)
     }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
 }
\ No newline at end of file
@@ -1,10 +1,12 @@
+use std::f32::consts::PI;
 use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
 use crate::core::color::ColorRgbF;
 use crate::core::ray::Ray;
-use crate::core::rehnda_math::{Vec3Ext, Vec3f};
+use crate::core::rehnda_math::Vec3f;
 use crate::hittable::HitResult;
 use crate::material::{Material, Scatter};
+use crate::sampler::{PixelSampler, warp};
 use crate::texture::solid::SolidTexture;
 use crate::texture::Texture;
 
@@ -27,18 +29,32 @@ impl IsotropicMaterial {
 }
 
 impl Material for IsotropicMaterial {
-    fn scatter(&self, ray_in: &Ray, hit_result: &HitResult) -> Option<Scatter> {
+    fn scatter(&self, ray_in: &Ray, hit_result: &HitResult, sampler: &mut PixelSampler) -> Option<Scatter> {
         let scattered_ray = Ray {
             origin: hit_result.hit_location,
-            direction: Vec3f::random_vec_in_unit_sphere(),
+            direction: warp::uniform_sphere(sampler.get_2d()),
             time: ray_in.time,
         };
         let attenuation = self.albedo.sample(&hit_result.uv, &hit_result.hit_location);
         Some(Scatter {
             scattered_ray,
             attenuation,
+            pdf: 1.0 / (4.0 * PI),
         })
     }
+
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    /// Scatters equally in every direction, so the phase function is constant over the sphere
+    fn eval(&self, _ray_in: &Ray, hit_result: &HitResult, _direction: &Vec3f) -> ColorRgbF {
+        self.albedo.sample(&hit_result.uv, &hit_result.hit_location) / (4.0 * PI)
+    }
+
+    fn pdf(&self, _ray_in: &Ray, _hit_result: &HitResult, _direction: &Vec3f) -> f32 {
+        1.0 / (4.0 * PI)
+    }
 }
 
 impl Debug for IsotropicMaterial {
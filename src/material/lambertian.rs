@@ -1,18 +1,21 @@
+use std::f32::consts::PI;
 use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
 use crate::core::color::ColorRgbF;
 use crate::core::ray::Ray;
+use crate::core::onb::Onb;
 use crate::core::rehnda_math::{Vec3f, Vec3Ext};
 use crate::hittable::HitResult;
 use crate::material::{Material, Scatter};
+use crate::sampler::{PixelSampler, warp};
 use crate::texture::solid::SolidTexture;
 use crate::texture::Texture;
 
-pub struct LambertianMaterial<T: Texture> {
+pub struct LambertianMaterial<T: Texture + ?Sized> {
     pub texture: Arc<T>,
 }
 
-impl<T: Texture> LambertianMaterial<T> {
+impl<T: Texture + ?Sized> LambertianMaterial<T> {
     pub fn new(texture: Arc<T>) -> LambertianMaterial<T> {
         LambertianMaterial {
             texture,
@@ -32,23 +35,33 @@ impl LambertianMaterial<SolidTexture> {
     }
 }
 
-impl<T: Texture> Debug for LambertianMaterial<T> {
+impl<T: Texture + ?Sized> Debug for LambertianMaterial<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "LambertianMaterial")
     }
 }
 
-impl<T: Texture> Material for LambertianMaterial<T> {
-    fn scatter(&self, ray_in: &Ray, hit_result: &HitResult) -> Option<Scatter> {
-        let mut scatter_direction = hit_result.normal + Vec3f::random_unit_vector();
-
-        if scatter_direction.is_near_zero() {
-            scatter_direction = hit_result.normal;
-        }
-
+impl<T: Texture + ?Sized> Material for LambertianMaterial<T> {
+    fn scatter(&self, ray_in: &Ray, hit_result: &HitResult, sampler: &mut PixelSampler) -> Option<Scatter> {
+        // cosine weighted directions cancel the BSDF's cosine term, leaving just the albedo as the attenuation
+        let scatter_direction = Onb::build_from_w(&hit_result.normal).local(&warp::cosine_hemisphere(sampler.get_2d()));
         Some(Scatter{
             scattered_ray: Ray{origin: hit_result.hit_location, direction: scatter_direction, time: ray_in.time},
             attenuation: self.texture.sample(&hit_result.uv, &hit_result.hit_location),
+            pdf: self.pdf(ray_in, hit_result, &scatter_direction),
         })
     }
+
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    fn eval(&self, _ray_in: &Ray, hit_result: &HitResult, direction: &Vec3f) -> ColorRgbF {
+        let cosine = hit_result.normal.dot(direction.unit_vector()).max(0.0);
+        self.texture.sample(&hit_result.uv, &hit_result.hit_location) * cosine / PI
+    }
+
+    fn pdf(&self, _ray_in: &Ray, hit_result: &HitResult, direction: &Vec3f) -> f32 {
+        hit_result.normal.dot(direction.unit_vector()).max(0.0) / PI
+    }
 }
\ No newline at end of file
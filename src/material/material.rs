@@ -1,20 +1,47 @@
 use std::fmt::{Debug};
 use crate::core::color::ColorRgbF;
 use crate::core::ray::Ray;
-use crate::core::rehnda_math::Point3f;
+use crate::core::rehnda_math::{Point3f, Vec3f};
 use crate::hittable::HitResult;
+use crate::sampler::PixelSampler;
 use crate::texture::Uv;
 
 pub struct Scatter {
     pub scattered_ray: Ray,
+    /// The BSDF times the cosine term divided by `pdf`
     pub attenuation: ColorRgbF,
+    /// Density over solid angle of choosing the scattered direction, zero for specular scatters
+    /// which can only choose a single direction
+    pub pdf: f32,
 }
 
 
 pub trait Material: Debug + Send + Sync {
-    fn scatter(&self, ray_in: &Ray, hit_result: &HitResult) -> Option<Scatter>;
+    fn scatter(&self, ray_in: &Ray, hit_result: &HitResult, sampler: &mut PixelSampler) -> Option<Scatter>;
 
-    fn emitted(&self, uv: &Uv, point: &Point3f) -> ColorRgbF {
+    fn emitted(&self, _uv: &Uv, _point: &Point3f) -> ColorRgbF {
         ColorRgbF::ZERO
     }
+
+    /// Whether the material emits light, emissive surfaces are collected into the scene's lights
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// Perfectly specular materials only scatter towards the directions they choose themselves,
+    /// so they can't be evaluated for arbitrary directions or lit by sampling lights directly
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    /// The BSDF times the cosine between `direction` and the normal, i.e. the fraction of light
+    /// arriving along `direction` that is scattered back along `ray_in`
+    fn eval(&self, _ray_in: &Ray, _hit_result: &HitResult, _direction: &Vec3f) -> ColorRgbF {
+        ColorRgbF::ZERO
+    }
+
+    /// Density over solid angle of [Material::scatter] choosing `direction`
+    fn pdf(&self, _ray_in: &Ray, _hit_result: &HitResult, _direction: &Vec3f) -> f32 {
+        0.0
+    }
 }
@@ -1,8 +1,10 @@
+use std::f32::consts::PI;
 use crate::core::color::ColorRgbF;
 use crate::core::ray::Ray;
 use crate::core::rehnda_math::{Vec3Ext, Vec3f};
 use crate::hittable::HitResult;
 use crate::material::{Material, Scatter};
+use crate::sampler::{PixelSampler, warp};
 
 #[derive(Debug, Copy, Clone)]
 pub struct MetalMaterial {
@@ -10,13 +12,63 @@ pub struct MetalMaterial {
     pub fuzz: f32,
 }
 
+impl MetalMaterial {
+    fn reflect_direction(ray_in: &Ray, hit_result: &HitResult) -> Vec3f {
+        ray_in.direction.unit_vector().reflect(hit_result.normal)
+    }
+}
+
 impl Material for MetalMaterial {
-    fn scatter(&self, ray_in: &Ray, hit_result: &HitResult) -> Option<Scatter> {
-        let reflect_dir = ray_in.direction.unit_vector().reflect(hit_result.normal);
-        let scatter_dir = reflect_dir + self.fuzz * Vec3f::random_vec_in_unit_sphere();
+    fn scatter(&self, ray_in: &Ray, hit_result: &HitResult, sampler: &mut PixelSampler) -> Option<Scatter> {
+        let reflect_dir = MetalMaterial::reflect_direction(ray_in, hit_result);
+        let scatter_dir = reflect_dir + self.fuzz * warp::uniform_ball(sampler.get_2d(), sampler.get_1d());
+        // fuzzed reflections that end up below the surface are absorbed
+        if scatter_dir.dot(hit_result.normal) <= 0.0 {
+            return None;
+        }
+
         Some(Scatter {
             scattered_ray: Ray { origin: hit_result.hit_location, direction: scatter_dir, time: ray_in.time },
             attenuation: self.albedo,
+            pdf: self.pdf(ray_in, hit_result, &scatter_dir),
         })
     }
-}
\ No newline at end of file
+
+    fn is_specular(&self) -> bool {
+        self.fuzz <= 0.0
+    }
+
+    /// The scatter's attenuation is the albedo, so the BSDF times the cosine is the albedo scaled
+    /// by the density of the fuzzed reflection
+    fn eval(&self, ray_in: &Ray, hit_result: &HitResult, direction: &Vec3f) -> ColorRgbF {
+        if direction.dot(hit_result.normal) <= 0.0 {
+            return ColorRgbF::ZERO;
+        }
+        self.albedo * self.pdf(ray_in, hit_result, direction)
+    }
+
+    /// Scattered directions point from the hit towards a point uniformly distributed in a ball of
+    /// radius `fuzz` around the mirror reflection's tip. The density of a direction is the volume
+    /// of the ball swept by the cone of directions around it, `(t_1^3 - t_0^3) / 3` per steradian
+    /// where the direction's line enters the ball at `t_0` and leaves at `t_1`, over the ball's volume.
+    fn pdf(&self, ray_in: &Ray, hit_result: &HitResult, direction: &Vec3f) -> f32 {
+        if self.is_specular() {
+            return 0.0;
+        }
+
+        let reflect_dir = MetalMaterial::reflect_direction(ray_in, hit_result);
+        let direction = direction.unit_vector();
+        let b = direction.dot(reflect_dir);
+        let discriminant = b * b - reflect_dir.length_squared() + self.fuzz * self.fuzz;
+        if discriminant <= 0.0 {
+            return 0.0;
+        }
+
+        let t_1 = b + discriminant.sqrt();
+        let t_0 = (b - discriminant.sqrt()).max(0.0);
+        if t_1 <= 0.0 {
+            return 0.0;
+        }
+        (t_1.powi(3) - t_0.powi(3)) / (4.0 * PI * self.fuzz.powi(3))
+    }
+}
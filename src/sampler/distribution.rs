@@ -0,0 +1,158 @@
+use glam::Vec2;
+
+/// Piecewise constant density over `[0, 1)` proportional to a tabulated function, sampled by
+/// inverting its cumulative distribution
+#[derive(Debug, Clone)]
+pub struct Distribution1D {
+    function: Vec<f32>,
+    /// `cdf[i]` is the probability of a sample falling before bucket `i`, with a final 1.0
+    cdf: Vec<f32>,
+    /// Integral of the function over `[0, 1)`
+    integral: f32,
+}
+
+impl Distribution1D {
+    /// Negative values are treated as zero. A function that is zero everywhere is sampled uniformly.
+    pub fn new(function: &[f32]) -> Distribution1D {
+        let function: Vec<f32> = function.iter().map(|value| value.max(0.0)).collect();
+        let count = function.len() as f32;
+        let mut cdf = Vec::with_capacity(function.len() + 1);
+        cdf.push(0.0);
+        let mut running_total = 0.0f64;
+        for value in &function {
+            running_total += *value as f64 / count as f64;
+            cdf.push(running_total as f32);
+        }
+        let integral = running_total as f32;
+        if integral > 0.0 {
+            for value in cdf.iter_mut() {
+                *value /= integral;
+            }
+        } else {
+            for (i, value) in cdf.iter_mut().enumerate() {
+                *value = i as f32 / count;
+            }
+        }
+        *cdf.last_mut().unwrap() = 1.0;
+        Distribution1D { function, cdf, integral }
+    }
+
+    pub fn count(&self) -> usize {
+        self.function.len()
+    }
+
+    pub fn integral(&self) -> f32 {
+        self.integral
+    }
+
+    /// Point in `[0, 1)` distributed proportionally to the function, with its bucket and density
+    pub fn sample_continuous(&self, u: f32) -> (f32, usize, f32) {
+        // last bucket whose cdf is at or below u, skipping buckets with zero probability
+        let bucket = self.cdf.partition_point(|value| *value <= u).clamp(1, self.count()) - 1;
+        let bucket_width = self.cdf[bucket + 1] - self.cdf[bucket];
+        let offset = if bucket_width > 0.0 { (u - self.cdf[bucket]) / bucket_width } else { 0.0 };
+        let value = ((bucket as f32 + offset) / self.count() as f32).min(1.0 - f32::EPSILON);
+        (value, bucket, self.pdf(bucket))
+    }
+
+    /// Density of the samples falling in `bucket`
+    pub fn pdf(&self, bucket: usize) -> f32 {
+        if self.integral > 0.0 {
+            self.function[bucket] / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Piecewise constant density over the unit square proportional to a tabulated function, sampled
+/// by choosing a row from the marginal distribution and then a column within the row
+#[derive(Debug, Clone)]
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// `function` holds `height` rows of `width` values, row by row
+    pub fn new(function: &[f32], width: usize, height: usize) -> Distribution2D {
+        let rows: Vec<Distribution1D> = function.chunks_exact(width)
+            .take(height)
+            .map(Distribution1D::new)
+            .collect();
+        let row_integrals: Vec<f32> = rows.iter().map(Distribution1D::integral).collect();
+        Distribution2D {
+            rows,
+            marginal: Distribution1D::new(&row_integrals),
+        }
+    }
+
+    /// Integral of the function over the unit square
+    pub fn integral(&self) -> f32 {
+        self.marginal.integral()
+    }
+
+    /// Point in the unit square, `x` along the rows and `y` across them, with its density
+    pub fn sample_continuous(&self, u: Vec2) -> (Vec2, f32) {
+        let (y, row, row_pdf) = self.marginal.sample_continuous(u.y);
+        let (x, _, column_pdf) = self.rows[row].sample_continuous(u.x);
+        (Vec2::new(x, y), row_pdf * column_pdf)
+    }
+
+    /// Density of sampling `point` in the unit square
+    pub fn pdf(&self, point: Vec2) -> f32 {
+        let row = ((point.y * self.rows.len() as f32) as usize).min(self.rows.len() - 1);
+        let row_distribution = &self.rows[row];
+        let column = ((point.x * row_distribution.count() as f32) as usize).min(row_distribution.count() - 1);
+        self.marginal.pdf(row) * row_distribution.pdf(column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_stay_within_the_unit_interval_with_matching_densities() {
+        for function in [vec![1.0, 3.0, 0.0, 4.0], vec![0.0, 0.0, 0.0], vec![5.0], vec![0.0, 2.0, -1.0]] {
+            let distribution = Distribution1D::new(&function);
+            for u in (0..1000).map(|i| i as f32 / 1000.0).chain([1.0 - f32::EPSILON]) {
+                let (value, bucket, pdf) = distribution.sample_continuous(u);
+                assert!((0.0..1.0).contains(&value), "{} for {:?}", value, function);
+                assert_eq!(bucket, (value * function.len() as f32) as usize);
+                assert!(pdf > 0.0, "sampled bucket {} of {:?} which can't be sampled", bucket, function);
+                assert_eq!(pdf, distribution.pdf(bucket));
+            }
+            let total = (0..function.len()).map(|bucket| distribution.pdf(bucket)).sum::<f32>() / function.len() as f32;
+            assert!((total - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn distribution_2d_pdf_matches_a_sample_histogram() {
+        let (width, height) = (4, 3);
+        let function = [
+            1.0, 2.0, 0.0, 4.0,
+            0.5, 0.0, 3.0, 1.0,
+            8.0, 1.0, 1.0, 0.0,
+        ];
+        let distribution = Distribution2D::new(&function, width, height);
+
+        let grid_size = 512;
+        let mut histogram = vec![0usize; width * height];
+        for i in 0..grid_size * grid_size {
+            let u = (Vec2::new((i % grid_size) as f32, (i / grid_size) as f32) + 0.5) / grid_size as f32;
+            let (point, pdf) = distribution.sample_continuous(u);
+            assert!((0.0..1.0).contains(&point.x) && (0.0..1.0).contains(&point.y));
+            assert_eq!(pdf, distribution.pdf(point));
+            histogram[(point.y * height as f32) as usize * width + (point.x * width as f32) as usize] += 1;
+        }
+
+        for (cell, count) in histogram.iter().enumerate() {
+            let centre = Vec2::new(((cell % width) as f32 + 0.5) / width as f32, ((cell / width) as f32 + 0.5) / height as f32);
+            let expected = distribution.pdf(centre) / (width * height) as f32;
+            let observed = *count as f32 / (grid_size * grid_size) as f32;
+            assert!((observed - expected).abs() < 1e-3, "cell {} was sampled {} of the time, expected {}", cell, observed, expected);
+        }
+    }
+}
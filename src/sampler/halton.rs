@@ -0,0 +1,73 @@
+use crate::core::rehnda_math::hash_u64;
+use crate::sampler::stratified::permutation_element;
+
+/// Bases of the Halton dimensions, later dimensions fall back to independent random numbers
+const PRIMES: [u32; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53,
+    59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 127, 131,
+    137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193, 197, 199, 211, 223,
+    227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307, 311,
+];
+/// Digits worth less than this don't change the f32 result
+const MIN_PLACE_VALUE: f64 = 1.0 / (1u64 << 26) as f64;
+
+/// Point `index` of Halton dimension `dimension`, with the digits of each place permuted by `seed`
+/// so neighbouring pixels don't share the same points and the large bases don't line points up
+/// along diagonals. `None` past the last supported dimension.
+pub fn sample(dimension: u32, index: u32, seed: u64) -> Option<f32> {
+    let base = *PRIMES.get(dimension as usize)?;
+    let value = scrambled_radical_inverse(base, index, seed) as f32;
+    // rounding to f32 can land exactly on one
+    Some(value.min(1.0 - f32::EPSILON / 2.0))
+}
+
+/// Mirrors the digits of `index` in `base` about the radix point, permuting the digits of every
+/// place differently. The zeros past the last digit of `index` are permuted too, until they're
+/// below f32 precision.
+fn scrambled_radical_inverse(base: u32, mut index: u32, seed: u64) -> f64 {
+    let inverse_base = 1.0 / base as f64;
+    let mut value = 0.0;
+    let mut place_value = inverse_base;
+    let mut place = 0u64;
+    while place_value > MIN_PLACE_VALUE {
+        let digit = index % base;
+        let place_seed = hash_u64(seed ^ place) as u32;
+        value += permutation_element(digit, base, place_seed) as f64 * place_value;
+        index /= base;
+        place_value *= inverse_base;
+        place += 1;
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_are_within_the_unit_interval() {
+        for dimension in 0..PRIMES.len() as u32 {
+            for index in (0..1000).chain([u32::MAX]) {
+                let value = sample(dimension, index, 42).unwrap();
+                assert!((0.0..1.0).contains(&value), "{} for dimension {} index {}", value, dimension, index);
+            }
+        }
+        assert!(sample(PRIMES.len() as u32, 0, 42).is_none());
+    }
+
+    #[test]
+    fn radical_inverse_prefixes_are_stratified() {
+        for (base, num_digits) in [(2u32, 10), (3, 6), (5, 4), (7, 3), (311, 1)] {
+            let count = base.pow(num_digits);
+            for seed in [0, 1, 99] {
+                // the leading digits of the first base^digits points are a permutation of all digit strings
+                let mut strata = vec![false; count as usize];
+                for index in 0..count {
+                    let stratum = (scrambled_radical_inverse(base, index, seed) * count as f64) as usize;
+                    assert!(!strata[stratum], "two of the first {} points in base {} share stratum {}", count, base, stratum);
+                    strata[stratum] = true;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,8 @@
+mod sampler;
+pub use sampler::*;
+
+pub mod distribution;
+pub mod halton;
+pub mod sobol;
+pub mod stratified;
+pub mod warp;
\ No newline at end of file
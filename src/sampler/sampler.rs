@@ -0,0 +1,143 @@
+use glam::Vec2;
+use serde::Deserialize;
+use crate::core::rehnda_math::{hash_u64, random, Rng, sample_rng};
+use crate::sampler::{halton, sobol, stratified};
+
+/// Dimensions used to generate the camera ray: the position in the pixel, on the lens and the time
+pub const CAMERA_DIMENSIONS: u32 = 5;
+/// Dimensions reserved for each bounce: choosing a light and a point on it, the material's scatter
+/// and Russian roulette. A bounce asking for more gets independent random numbers rather than
+/// eating into the next bounce's dimensions.
+pub const DIMENSIONS_PER_BOUNCE: u32 = 8;
+
+/// How the random numbers of a pixel's samples are spread out. Better spread patterns give each
+/// pixel a more even coverage of the paths it averages, so renders converge with fewer samples.
+#[derive(Debug, Copy, Clone, Deserialize, Default, PartialEq, Eq)]
+pub enum Sampler {
+    /// Every number is independently random
+    Independent,
+    /// Each dimension is split into one stratum per sample (a square grid for pairs of
+    /// dimensions), and every sample takes a jittered point in its own stratum
+    Stratified,
+    /// The Halton sequence with its digits randomly permuted per pixel and dimension
+    Halton,
+    /// The Sobol sequence with hash based Owen scrambling per pixel and dimension. Converges best
+    /// with a power of two number of samples.
+    #[default]
+    Sobol,
+}
+
+impl Sampler {
+    /// Sampler for sample `sample_index` of the `samples_per_pixel` samples of pixel `pixel_index`
+    pub fn pixel_sampler(&self, seed: u64, pixel_index: u64, sample_index: usize, samples_per_pixel: usize) -> PixelSampler {
+        PixelSampler {
+            sampler: *self,
+            pixel_seed: hash_u64(seed ^ hash_u64(pixel_index)),
+            sample_index: sample_index as u32,
+            samples_per_pixel: samples_per_pixel as u32,
+            dimension: 0,
+            dimension_end: CAMERA_DIMENSIONS,
+            rng: sample_rng(seed, pixel_index, sample_index as u64),
+        }
+    }
+}
+
+/// Supplies the numbers for one sample of one pixel, one dimension at a time. The same dimension
+/// across all the samples of a pixel follows the sampler's pattern.
+pub struct PixelSampler {
+    sampler: Sampler,
+    pixel_seed: u64,
+    sample_index: u32,
+    samples_per_pixel: u32,
+    dimension: u32,
+    dimension_end: u32,
+    rng: Rng,
+}
+
+impl PixelSampler {
+    /// Moves on to the dimensions reserved for the bounce at the `bounce`th hit of the path, the
+    /// camera ray's hit being bounce zero
+    pub fn start_bounce(&mut self, bounce: usize) {
+        self.dimension = CAMERA_DIMENSIONS + bounce as u32 * DIMENSIONS_PER_BOUNCE;
+        self.dimension_end = self.dimension + DIMENSIONS_PER_BOUNCE;
+    }
+
+    pub fn get_1d(&mut self) -> f32 {
+        if self.dimension >= self.dimension_end {
+            return random(&mut self.rng);
+        }
+        let dimension = self.dimension;
+        self.dimension += 1;
+
+        let dimension_seed = self.dimension_seed(dimension);
+        match self.sampler {
+            Sampler::Independent => random(&mut self.rng),
+            Sampler::Stratified => {
+                let jitter = random(&mut self.rng);
+                stratified::sample_1d(self.sample_index, self.samples_per_pixel, dimension_seed, jitter)
+            }
+            Sampler::Halton => halton::sample(dimension, self.sample_index, dimension_seed)
+                .unwrap_or_else(|| random(&mut self.rng)),
+            Sampler::Sobol => sobol::sample_1d(self.sample_index, dimension_seed),
+        }
+    }
+
+    pub fn get_2d(&mut self) -> Vec2 {
+        if self.dimension + 2 > self.dimension_end {
+            return Vec2::new(random(&mut self.rng), random(&mut self.rng));
+        }
+        let dimension = self.dimension;
+        self.dimension += 2;
+
+        let dimension_seed = self.dimension_seed(dimension);
+        match self.sampler {
+            Sampler::Independent => Vec2::new(random(&mut self.rng), random(&mut self.rng)),
+            Sampler::Stratified => {
+                let jitter = Vec2::new(random(&mut self.rng), random(&mut self.rng));
+                stratified::sample_2d(self.sample_index, self.samples_per_pixel, dimension_seed, jitter)
+            }
+            Sampler::Halton => {
+                let x = halton::sample(dimension, self.sample_index, dimension_seed);
+                let y = halton::sample(dimension + 1, self.sample_index, self.dimension_seed(dimension + 1));
+                Vec2::new(x.unwrap_or_else(|| random(&mut self.rng)), y.unwrap_or_else(|| random(&mut self.rng)))
+            }
+            Sampler::Sobol => sobol::sample_2d(self.sample_index, dimension_seed),
+        }
+    }
+
+    /// Index in `0..count` chosen with a single dimension
+    pub fn get_index(&mut self, count: usize) -> usize {
+        ((self.get_1d() * count as f32) as usize).min(count - 1)
+    }
+
+    fn dimension_seed(&self, dimension: u32) -> u64 {
+        hash_u64(self.pixel_seed ^ hash_u64(dimension as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_sampler_stays_within_the_unit_interval() {
+        for sampler in [Sampler::Independent, Sampler::Stratified, Sampler::Halton, Sampler::Sobol] {
+            for samples_per_pixel in [1, 7, 16] {
+                for sample_index in 0..samples_per_pixel {
+                    let mut pixel_sampler = sampler.pixel_sampler(3, 12345, sample_index, samples_per_pixel);
+                    // past the camera dimensions into the bounces, and past the last Halton dimension
+                    for bounce in [0, 1, 10] {
+                        pixel_sampler.start_bounce(bounce);
+                        for _ in 0..DIMENSIONS_PER_BOUNCE + 2 {
+                            let value = pixel_sampler.get_1d();
+                            assert!((0.0..1.0).contains(&value), "{:?} gave {}", sampler, value);
+                            let point = pixel_sampler.get_2d();
+                            assert!((0.0..1.0).contains(&point.x) && (0.0..1.0).contains(&point.y), "{:?} gave {}", sampler, point);
+                            assert!(pixel_sampler.get_index(3) < 3);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use glam::Vec2;
+
+/// Scrambled point `index` of the one dimensional Sobol sequence (the van der Corput sequence),
+/// with the points shuffled and scrambled by `seed`
+pub fn sample_1d(index: u32, seed: u64) -> f32 {
+    let (index_seed, x_seed) = ((seed >> 32) as u32, seed as u32);
+    let index = nested_uniform_scramble(index, index_seed);
+    to_unit_float(nested_uniform_scramble(index.reverse_bits(), x_seed))
+}
+
+/// Scrambled point `index` of the first two Sobol dimensions, which form a (0,2) sequence so every
+/// power of two prefix is stratified over all the ways of splitting the square into equal boxes
+pub fn sample_2d(index: u32, seed: u64) -> Vec2 {
+    let (index_seed, x_seed) = ((seed >> 32) as u32, seed as u32);
+    let y_seed = x_seed.wrapping_mul(0x9e3779b9) ^ index_seed;
+    let index = nested_uniform_scramble(index, index_seed);
+    Vec2::new(
+        to_unit_float(nested_uniform_scramble(index.reverse_bits(), x_seed)),
+        to_unit_float(nested_uniform_scramble(sobol_second_dimension(index), y_seed)),
+    )
+}
+
+fn sobol_second_dimension(mut index: u32) -> u32 {
+    let mut result = 0;
+    let mut direction = 1u32 << 31;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= direction;
+        }
+        index >>= 1;
+        direction ^= direction >> 1;
+    }
+    result
+}
+
+/// Owen scrambling in base 2, randomly flipping each bit based on the bits above it, from Burley's
+/// "Practical Hash-based Owen Scrambling"
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+/// Maps the top 24 bits to a float in the unit interval, which an f32 represents exactly
+fn to_unit_float(x: u32) -> f32 {
+    (x >> 8) as f32 / (1u32 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEEDS: [u64; 3] = [0, 1, 0x1234_5678_9abc_def0];
+
+    #[test]
+    fn samples_are_within_the_unit_interval() {
+        for seed in SEEDS {
+            for index in (0..4096).chain([u32::MAX - 1, u32::MAX]) {
+                assert!((0.0..1.0).contains(&sample_1d(index, seed)));
+                let point = sample_2d(index, seed);
+                assert!((0.0..1.0).contains(&point.x) && (0.0..1.0).contains(&point.y), "{} at {}", point, index);
+            }
+        }
+    }
+
+    #[test]
+    fn scrambling_permutes_the_leading_bits() {
+        for seed in [0, 7, 0xdead_beef] {
+            let mut seen = vec![false; 1 << 10];
+            for prefix in 0..1u32 << 10 {
+                let scrambled = nested_uniform_scramble(prefix << 22, seed) >> 22;
+                assert!(!seen[scrambled as usize], "{} scrambled twice to {}", seed, scrambled);
+                seen[scrambled as usize] = true;
+            }
+        }
+    }
+
+    #[test]
+    fn power_of_two_prefixes_are_stratified() {
+        for seed in SEEDS {
+            for log_count in 0..=8 {
+                let count = 1usize << log_count;
+                let mut strata = vec![false; count];
+                for index in 0..count as u32 {
+                    let stratum = (sample_1d(index, seed) * count as f32) as usize;
+                    assert!(!strata[stratum], "two of {} 1d samples in stratum {}", count, stratum);
+                    strata[stratum] = true;
+                }
+
+                // every split of the square into `count` equal boxes has one point in each box
+                for log_columns in 0..=log_count {
+                    let (columns, rows) = (1usize << log_columns, count >> log_columns);
+                    let mut boxes = vec![false; count];
+                    for index in 0..count as u32 {
+                        let point = sample_2d(index, seed);
+                        let cell = (point.y * rows as f32) as usize * columns + (point.x * columns as f32) as usize;
+                        assert!(!boxes[cell], "two of {} 2d samples in box {} of {}x{}", count, cell, columns, rows);
+                        boxes[cell] = true;
+                    }
+                }
+            }
+        }
+    }
+}
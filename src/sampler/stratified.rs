@@ -0,0 +1,103 @@
+use glam::Vec2;
+
+/// Jittered point of sample `index` of `count`, each sample taking its own stratum of the unit
+/// interval in an order shuffled by `seed`
+pub fn sample_1d(index: u32, count: u32, seed: u64, jitter: f32) -> f32 {
+    let stratum = permutation_element(index, count, seed as u32);
+    (stratum as f32 + jitter) / count as f32
+}
+
+/// Jittered point of sample `index` of `count` in a shuffled square grid of strata. When `count`
+/// isn't a square the samples left over after filling the largest grid that fits use the jitter
+/// as an unstratified point.
+pub fn sample_2d(index: u32, count: u32, seed: u64, jitter: Vec2) -> Vec2 {
+    let grid_size = (count as f32).sqrt() as u32;
+    let stratum = permutation_element(index, count, seed as u32);
+    if stratum >= grid_size * grid_size {
+        return jitter;
+    }
+    let cell = Vec2::new((stratum % grid_size) as f32, (stratum / grid_size) as f32);
+    (cell + jitter) / grid_size as f32
+}
+
+/// Element `index` of a random permutation of `0..length` chosen by `seed`, without storing the
+/// permutation. From Kensler's "Correlated Multi-Jittered Sampling".
+pub fn permutation_element(mut index: u32, length: u32, seed: u32) -> u32 {
+    let mut mask = length - 1;
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+    // the hash permutes 0..=mask, so keep going until it lands within the length
+    loop {
+        index ^= seed;
+        index = index.wrapping_mul(0xe170893d);
+        index ^= seed >> 16;
+        index ^= (index & mask) >> 4;
+        index ^= seed >> 8;
+        index = index.wrapping_mul(0x0929eb3f);
+        index ^= seed >> 23;
+        index ^= (index & mask) >> 1;
+        index = index.wrapping_mul(1 | seed >> 27);
+        index = index.wrapping_mul(0x6935fa69);
+        index ^= (index & mask) >> 11;
+        index = index.wrapping_mul(0x74dcb303);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0x9e501cc3);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0xc860a3df);
+        index &= mask;
+        index ^= index >> 5;
+        if index < length {
+            break;
+        }
+    }
+    index.wrapping_add(seed) % length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permutation_elements_are_a_bijection() {
+        for length in [1, 2, 3, 5, 7, 10, 16, 17, 100, 1000, 4097] {
+            for seed in [0, 1, 0x8000_0000, 0xdead_beef] {
+                let mut seen = vec![false; length as usize];
+                for index in 0..length {
+                    let element = permutation_element(index, length, seed);
+                    assert!(element < length, "{} out of range for length {}", element, length);
+                    assert!(!seen[element as usize], "{} repeated for length {} seed {}", element, length, seed);
+                    seen[element as usize] = true;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn samples_take_one_stratum_each() {
+        for count in [1, 3, 9, 10, 64] {
+            let grid_size = (count as f32).sqrt() as u32;
+            let mut strata = vec![false; count as usize];
+            let mut cells = vec![false; (grid_size * grid_size) as usize];
+            for index in 0..count {
+                let value = sample_1d(index, count, 7, 0.5);
+                assert!((0.0..1.0).contains(&value));
+                let stratum = (value * count as f32) as usize;
+                assert!(!strata[stratum]);
+                strata[stratum] = true;
+
+                let point = sample_2d(index, count, 7, Vec2::splat(0.999));
+                assert!((0.0..1.0).contains(&point.x) && (0.0..1.0).contains(&point.y), "{} for {} of {}", point, index, count);
+                // samples left over after filling the grid aren't stratified
+                if permutation_element(index, count, 7) < grid_size * grid_size {
+                    let cell = (point.y * grid_size as f32) as usize * grid_size as usize + (point.x * grid_size as f32) as usize;
+                    assert!(!cells[cell]);
+                    cells[cell] = true;
+                }
+            }
+            assert!(cells.iter().all(|&taken| taken), "grid of {} samples isn't filled", count);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+use glam::Vec2;
+use crate::core::rehnda_math::Vec3f;
+
+/// Maps the unit square onto the unit disk, keeping neighbouring points close together so
+/// stratified samples stay stratified (Shirley and Chiu's concentric mapping)
+pub fn concentric_disk(u: Vec2) -> Vec2 {
+    let offset = 2.0 * u - Vec2::ONE;
+    if offset == Vec2::ZERO {
+        return Vec2::ZERO;
+    }
+    let (radius, theta) = if offset.x.abs() > offset.y.abs() {
+        (offset.x, FRAC_PI_4 * (offset.y / offset.x))
+    } else {
+        (offset.y, FRAC_PI_2 - FRAC_PI_4 * (offset.x / offset.y))
+    };
+    radius * Vec2::new(theta.cos(), theta.sin())
+}
+
+/// Uniformly distributed direction over the unit sphere
+pub fn uniform_sphere(u: Vec2) -> Vec3f {
+    let z = 1.0 - 2.0 * u.x;
+    let radius = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.y;
+    Vec3f::new(radius * phi.cos(), radius * phi.sin(), z)
+}
+
+/// Direction in the hemisphere about the z axis with a density proportional to its cosine with
+/// the axis, by projecting a point on the unit disk up onto the hemisphere
+pub fn cosine_hemisphere(u: Vec2) -> Vec3f {
+    let disk_point = concentric_disk(u);
+    let z = (1.0 - disk_point.length_squared()).max(0.0).sqrt();
+    Vec3f::new(disk_point.x, disk_point.y, z)
+}
+
+/// Uniformly distributed point in the unit ball
+pub fn uniform_ball(u: Vec2, radius_sample: f32) -> Vec3f {
+    radius_sample.cbrt() * uniform_sphere(u)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Points at the centres of a `size` by `size` grid over the unit square, plus its corners
+    fn grid(size: usize) -> impl Iterator<Item = Vec2> {
+        let centres = (0..size * size).map(move |i| (Vec2::new((i % size) as f32, (i / size) as f32) + 0.5) / size as f32);
+        centres.chain([Vec2::ZERO, Vec2::X, Vec2::Y, Vec2::ONE])
+    }
+
+    #[test]
+    fn disk_points_stay_within_the_disk_and_cover_it_evenly() {
+        let size = 200;
+        let mut sum_radius_squared = 0.0;
+        for u in grid(size).take(size * size) {
+            let point = concentric_disk(u);
+            assert!(point.length() <= 1.0 + 1e-6, "{} mapped outside the disk to {}", u, point);
+            sum_radius_squared += point.length_squared();
+        }
+        // the squared radius of uniform points on the disk averages a half
+        assert!((sum_radius_squared / (size * size) as f32 - 0.5).abs() < 1e-3);
+        assert!(grid(1).skip(1).all(|u| concentric_disk(u).length() <= 1.0 + 1e-6));
+    }
+
+    #[test]
+    fn directions_are_unit_length_and_distributed_as_documented() {
+        let size = 200;
+        let (mut sum_sphere_z, mut sum_hemisphere_z) = (0.0, 0.0);
+        for u in grid(size) {
+            let sphere = uniform_sphere(u);
+            assert!((sphere.length() - 1.0).abs() < 1e-5);
+            let hemisphere = cosine_hemisphere(u);
+            assert!((hemisphere.length() - 1.0).abs() < 1e-5);
+            assert!(hemisphere.z >= 0.0);
+            assert!(uniform_ball(u, 0.999).length() <= 1.0);
+            sum_sphere_z += sphere.z;
+            sum_hemisphere_z += hemisphere.z;
+        }
+        let count = (size * size + 4) as f32;
+        // uniform over the sphere averages zero, cosine weighted over the hemisphere averages 2/3
+        assert!((sum_sphere_z / count).abs() < 1e-3);
+        assert!((sum_hemisphere_z / count - 2.0 / 3.0).abs() < 1e-3);
+    }
+}
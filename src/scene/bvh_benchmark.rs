@@ -0,0 +1,64 @@
+use std::hint::black_box;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use log::info;
+use crate::acceleration::bvh::BvhLayout;
+use crate::core::ray::Ray;
+use crate::core::onb::Onb;
+use crate::error::RehndaError;
+use crate::sampler::{Sampler, warp};
+use crate::scene::Scene;
+use crate::scene::scene_builder::{build_scene, load_scene_description};
+use crate::scene::settings::RehndaSettings;
+
+/// Number of times each layout traces the ray set, the fastest pass is reported
+const BENCHMARK_PASSES: usize = 3;
+
+/// Compares the BVH layouts on the scene of `settings` by building the scene with each layout and
+/// timing one camera ray per pixel plus a diffuse bounce from every hit.
+///
+/// Both builds generate any random scene content from the settings' seed, so the layouts are
+/// compared on the same scene.
+pub fn run_bvh_benchmark(settings: &RehndaSettings) -> Result<(), RehndaError> {
+    let scene_description = load_scene_description(Path::new(&settings.scene_file))?;
+    for layout in [BvhLayout::Tree, BvhLayout::Flat] {
+        let mut scene_options = settings.scene_options();
+        scene_options.acceleration_settings.bvh_layout = layout;
+
+        let build_start = Instant::now();
+        let scene = build_scene(&scene_description, &scene_options)?;
+        let build_duration = build_start.elapsed();
+
+        let (num_rays, trace_duration) = (0..BENCHMARK_PASSES)
+            .map(|_| trace_rays(&scene, settings.resolution(), settings.seed))
+            .min_by_key(|(_, duration)| *duration)
+            .unwrap();
+        info!("{:?} layout: built in {:?}, traced {} rays in {:?} ({:.2} Mrays/s)",
+              layout, build_duration, num_rays, trace_duration, num_rays as f64 / trace_duration.as_secs_f64() / 1e6);
+    }
+    Ok(())
+}
+
+fn trace_rays(scene: &Scene, (image_width, image_height): (usize, usize), seed: u64) -> (usize, Duration) {
+    let trace_start = Instant::now();
+    let mut num_rays = 0;
+    for j in 0..image_height {
+        for i in 0..image_width {
+            let u = i as f32 / (image_width - 1) as f32;
+            let v = j as f32 / (image_height - 1) as f32;
+            let mut sampler = Sampler::Independent.pixel_sampler(seed, (i + j * image_width) as u64, 0, 1);
+            let ray = scene.camera.get_ray(u, v, &mut sampler);
+            num_rays += 1;
+            if let Some(hit) = black_box(scene.world.hit(&ray, 0.001, f32::MAX)) {
+                let bounce = Ray {
+                    origin: hit.hit_location,
+                    direction: Onb::build_from_w(&hit.normal).local(&warp::cosine_hemisphere(sampler.get_2d())),
+                    time: ray.time,
+                };
+                black_box(scene.world.hit(&bounce, 0.001, f32::MAX));
+                num_rays += 1;
+            }
+        }
+    }
+    (num_rays, trace_start.elapsed())
+}
@@ -1,5 +1,6 @@
 use crate::core::ray::Ray;
-use crate::core::rehnda_math::{Point3f, random_in_range, Vec3Ext, Vec3f};
+use crate::core::rehnda_math::{Point3f, Vec3Ext, Vec3f};
+use crate::sampler::{PixelSampler, warp};
 
 #[derive(Debug, Copy, Clone)]
 pub struct Camera {
@@ -62,13 +63,13 @@ impl Camera {
         }
     }
 
-    pub fn get_ray(&self, s: f32, t: f32) -> Ray {
-        let random_offset = self.lens_radius * Vec3f::random_vec_in_unit_disk();
-        let camera_frame_offset = self.u_axis * random_offset.x + self.v_axis * random_offset.y;
+    pub fn get_ray(&self, s: f32, t: f32, sampler: &mut PixelSampler) -> Ray {
+        let lens_point = self.lens_radius * warp::concentric_disk(sampler.get_2d());
+        let camera_frame_offset = self.u_axis * lens_point.x + self.v_axis * lens_point.y;
         Ray {
             origin: self.origin + camera_frame_offset,
             direction: self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - camera_frame_offset,
-            time: random_in_range(self.shutter_open_time, self.shutter_close_time)
+            time: self.shutter_open_time + sampler.get_1d() * (self.shutter_close_time - self.shutter_open_time)
         }
     }
 }
\ No newline at end of file
@@ -1,6 +1,11 @@
 mod scene;
 pub use scene::*;
 
+pub mod bvh_benchmark;
 pub mod camera;
+pub mod obj_loader;
 pub mod scene_builder;
-pub mod settings;
\ No newline at end of file
+pub mod scene_description;
+pub mod scene_info;
+pub mod settings;
+pub mod settings_override;
\ No newline at end of file
@@ -0,0 +1,211 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+use log::info;
+use crate::core::rehnda_math::{Point3f, Vec3f};
+use crate::error::RehndaError;
+use crate::hittable::triangle_mesh::{MeshData, MeshVertex, TriangleMesh};
+use crate::material::Material;
+use crate::scene::settings::AccelerationSettings;
+use crate::texture::Uv;
+
+/// Loads the geometry of a Wavefront OBJ file as a single [TriangleMesh].
+///
+/// Polygons with more than three vertices are triangulated as a fan. `.mtl` files are not read,
+/// instead the name given to each `usemtl` statement is passed to `material_for_group` (`None` for
+/// faces declared before any `usemtl`) so it can be mapped onto the materials of the scene.
+pub fn load_obj(path: &Path, acceleration_settings: &AccelerationSettings, material_for_group: impl Fn(Option<&str>) -> Result<Arc<dyn Material>, RehndaError>) -> Result<TriangleMesh, RehndaError> {
+    let file = File::open(path).map_err(|source| RehndaError::Read { path: path.to_path_buf(), source })?;
+    let reader = BufReader::new(file);
+
+    let mut positions: Vec<Point3f> = Vec::new();
+    let mut normals: Vec<Vec3f> = Vec::new();
+    let mut uvs: Vec<Uv> = Vec::new();
+    let mut faces: Vec<([MeshVertex; 3], Arc<dyn Material>)> = Vec::new();
+    let mut current_material = material_for_group(None)?;
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|source| RehndaError::Read { path: path.to_path_buf(), source })?;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => positions.push(parse_vec3(&mut tokens, path, line_index)?),
+            Some("vn") => normals.push(parse_vec3(&mut tokens, path, line_index)?),
+            Some("vt") => {
+                let u = parse_float(tokens.next(), path, line_index)?;
+                let v = tokens.next().map_or(Ok(0.0), |token| parse_float(Some(token), path, line_index))?;
+                uvs.push(Uv::new(u, v));
+            }
+            Some("f") => {
+                let polygon: Vec<MeshVertex> = tokens
+                    .map(|token| parse_face_vertex(token, positions.len(), uvs.len(), normals.len(), path, line_index))
+                    .collect::<Result<_, _>>()?;
+                if polygon.len() < 3 {
+                    return Err(mesh_error(path, line_index, "face has fewer than 3 vertices".to_string()));
+                }
+                for i in 1..polygon.len() - 1 {
+                    faces.push(([polygon[0], polygon[i], polygon[i + 1]], current_material.clone()));
+                }
+            }
+            Some("usemtl") => current_material = material_for_group(tokens.next())?,
+            // groups, objects, smoothing groups, material libraries and comments are ignored
+            _ => {}
+        }
+    }
+
+    info!("Loaded mesh {:?} with {} vertices and {} triangles", path, positions.len(), faces.len());
+    if faces.is_empty() {
+        return Err(RehndaError::InvalidGeometry(format!("mesh {:?} has no faces", path)));
+    }
+    let mesh_data = Arc::new(MeshData {
+        positions,
+        normals,
+        uvs,
+    });
+    let mesh = TriangleMesh::new(mesh_data, faces, acceleration_settings.bvh_builder, acceleration_settings.bvh_layout);
+    info!("Mesh BVH: {}", mesh.bvh_stats());
+    Ok(mesh)
+}
+
+fn mesh_error(path: &Path, line_index: usize, message: String) -> RehndaError {
+    RehndaError::InvalidMesh { path: path.to_path_buf(), line: line_index + 1, message }
+}
+
+fn parse_float(token: Option<&str>, path: &Path, line_index: usize) -> Result<f32, RehndaError> {
+    token.and_then(|value| value.parse::<f32>().ok())
+        .ok_or_else(|| mesh_error(path, line_index, "expected a number".to_string()))
+}
+
+fn parse_vec3<'a>(tokens: &mut impl Iterator<Item=&'a str>, path: &Path, line_index: usize) -> Result<Vec3f, RehndaError> {
+    Ok(Vec3f::new(
+        parse_float(tokens.next(), path, line_index)?,
+        parse_float(tokens.next(), path, line_index)?,
+        parse_float(tokens.next(), path, line_index)?,
+    ))
+}
+
+/// Parses a `v`, `v/vt`, `v//vn` or `v/vt/vn` face vertex, resolving negative (relative) indices.
+fn parse_face_vertex(token: &str, num_positions: usize, num_uvs: usize, num_normals: usize, path: &Path, line_index: usize) -> Result<MeshVertex, RehndaError> {
+    let resolve = |index: &str, count: usize| -> Result<usize, RehndaError> {
+        let index: i64 = index.parse()
+            .map_err(|_| mesh_error(path, line_index, format!("invalid face index '{}'", token)))?;
+        let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+        if resolved < 0 || resolved >= count as i64 {
+            return Err(mesh_error(path, line_index, format!("face index '{}' is out of range", token)));
+        }
+        Ok(resolved as usize)
+    };
+
+    let mut parts = token.split('/');
+    let position = resolve(parts.next().unwrap_or(""), num_positions)?;
+    let uv = parts.next().filter(|part| !part.is_empty()).map(|part| resolve(part, num_uvs)).transpose()?;
+    let normal = parts.next().filter(|part| !part.is_empty()).map(|part| resolve(part, num_normals)).transpose()?;
+
+    Ok(MeshVertex {
+        position,
+        normal,
+        uv,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use super::*;
+    use crate::core::color::ColorRgbF;
+    use crate::core::ray::Ray;
+    use crate::hittable::Hittable;
+    use crate::material::diffuse_light::DiffuseLight;
+    use crate::material::lambertian::LambertianMaterial;
+
+    /// Writes `contents` to a temporary OBJ file and loads it, with the `light` group emissive
+    fn load(name: &str, contents: &str) -> (Result<TriangleMesh, RehndaError>, PathBuf) {
+        let path = std::env::temp_dir().join(format!("rehnda_obj_loader_{}_{}.obj", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        let mesh = load_obj(&path, &AccelerationSettings::default(), |group| Ok(match group {
+            Some("light") => Arc::new(DiffuseLight::new_solid_light(&ColorRgbF::ONE)) as Arc<dyn Material>,
+            _ => Arc::new(LambertianMaterial::new_with_solid_color(&ColorRgbF::ONE)),
+        }));
+        fs::remove_file(&path).unwrap();
+        (mesh, path)
+    }
+
+    fn hits_down_at(mesh: &TriangleMesh, x: f32, y: f32) -> bool {
+        let ray = Ray { origin: Vec3f::new(x, y, 1.0), direction: Vec3f::new(0.0, 0.0, -1.0), time: 0.0 };
+        mesh.hit(&ray, 0.001, f32::MAX).is_some()
+    }
+
+    #[test]
+    fn polygons_are_triangulated_as_fans() {
+        let (mesh, _) = load("fan", "\
+v 0 0 0
+v 2 0 0
+v 3 2 0
+v 1 3 0
+v -1 2 0
+f 1 2 3 4 5
+");
+        let mesh = mesh.unwrap();
+        assert_eq!(mesh.bvh_stats().num_primitives, 3);
+        // the centroids of the three triangles fanning out from the first vertex
+        for (x, y) in [(5.0 / 3.0, 2.0 / 3.0), (4.0 / 3.0, 5.0 / 3.0), (0.0, 5.0 / 3.0)] {
+            assert!(hits_down_at(&mesh, x, y), "missed ({}, {})", x, y);
+        }
+        assert!(!hits_down_at(&mesh, 3.0, 0.5));
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_latest_vertex() {
+        let (mesh, _) = load("negative", "\
+v 10 10 10
+vt 0.5 0.5
+vn 0 0 1
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0 0
+vn 0 0 1
+f -3/-2/-1 -2/-1/-2 -1//-1
+usemtl light
+v 5 5 0
+v 6 5 0
+v 5 6 0
+f -3 -2 -1
+");
+        let mesh = mesh.unwrap();
+        assert_eq!(mesh.bvh_stats().num_primitives, 2);
+        assert!(hits_down_at(&mesh, 0.25, 0.25));
+        assert!(hits_down_at(&mesh, 5.25, 5.25));
+        assert!(!hits_down_at(&mesh, 9.9, 9.9));
+        assert!(mesh.light().is_some());
+
+        let vertex = parse_face_vertex("-1/-2/-3", 4, 2, 3, Path::new("test.obj"), 0).unwrap();
+        assert_eq!((vertex.position, vertex.uv, vertex.normal), (3, Some(0), Some(0)));
+        let vertex = parse_face_vertex("2//1", 4, 2, 3, Path::new("test.obj"), 0).unwrap();
+        assert_eq!((vertex.position, vertex.uv, vertex.normal), (1, None, Some(0)));
+    }
+
+    #[test]
+    fn errors_report_the_line_they_are_on() {
+        let cases = [
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\n# comment\nf 1 2 4\n", 5, "out of range"),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 -4\n", 4, "out of range"),
+            ("v 0 0 0\nv 1 0 x\n", 2, "expected a number"),
+            ("v 0 0 0\nv 1 0 0\n\nf 1 2\n", 4, "fewer than 3 vertices"),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 a\n", 4, "invalid face index 'a'"),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 0\n", 4, "out of range"),
+        ];
+        for (contents, expected_line, expected_message) in cases {
+            match load("errors", contents) {
+                (Err(RehndaError::InvalidMesh { path: error_path, line, message }), path) => {
+                    assert_eq!(error_path, path);
+                    assert_eq!(line, expected_line, "{}", message);
+                    assert!(message.contains(expected_message), "{}", message);
+                }
+                (result, _) => panic!("expected a mesh error for {:?}, got {:?}", contents, result.err()),
+            }
+        }
+        assert!(matches!(load("no_faces", "v 0 0 0\n").0, Err(RehndaError::InvalidGeometry(_))));
+    }
+}
@@ -1,11 +1,45 @@
 use std::sync::Arc;
+use crate::acceleration::bvh::BvhStats;
 use crate::core::color::ColorRgbF;
+use crate::core::rehnda_math::Vec3f;
 use crate::hittable::Hittable;
+use crate::light::{EnvironmentLight, PunctualLight};
 use crate::scene::camera::Camera;
 
 #[derive(Clone)]
 pub struct Scene {
     pub camera: Camera,
     pub world: Arc<dyn Hittable>,
-    pub background: ColorRgbF,
+    /// Emissive objects of the world, sampled directly by integrators using next event estimation
+    pub lights: Vec<Arc<dyn Hittable>>,
+    /// Point, spot and directional lights, which can only be found by sampling them directly
+    pub punctual_lights: Vec<Arc<dyn PunctualLight>>,
+    pub background: Background,
+    /// Shape of the BVH over the world's objects
+    pub bvh_stats: BvhStats,
+}
+
+/// What rays that escape the world see
+#[derive(Clone)]
+pub enum Background {
+    Color(ColorRgbF),
+    /// Also sampled as a light by integrators using next event estimation
+    Environment(Arc<dyn EnvironmentLight>),
+}
+
+impl Background {
+    pub fn radiance(&self, direction: &Vec3f) -> ColorRgbF {
+        match self {
+            Background::Color(color) => *color,
+            Background::Environment(environment) => environment.radiance(direction),
+        }
+    }
+
+    /// The environment to sample as a light, if there is one
+    pub fn environment(&self) -> Option<&dyn EnvironmentLight> {
+        match self {
+            Background::Color(_) => None,
+            Background::Environment(environment) => Some(environment.as_ref()),
+        }
+    }
 }
\ No newline at end of file
@@ -1,431 +1,466 @@
-use std::iter::once;
+use std::collections::HashMap;
+use std::fs;
 use std::path::Path;
-use std::slice;
 use std::sync::Arc;
-use crate::acceleration::bvh::BvhNode;
+use glam::{Quat, Vec3};
+use log::info;
+use crate::acceleration::bvh::build_bvh;
 use crate::core::color::ColorRgbF;
-use crate::core::rehnda_math::{Point3f, random_in_range, Vec3Ext, Vec3f};
+use crate::core::rehnda_math::{Point3f, random_in_range, Rng, seeded_rng, Vec3Ext, Vec3f};
+use crate::error::RehndaError;
+use crate::hittable::animated_transform::{AnimatedTransform, Keyframe};
 use crate::hittable::box_hittable::BoxHittable;
 use crate::hittable::constant_medium::ConstantMedium;
 use crate::hittable::Hittable;
-use crate::hittable::rotate_y::RotateY;
+use crate::hittable::moving_sphere::MovingSphere;
 use crate::hittable::sphere::Sphere;
-use crate::hittable::translate::Translate;
+use crate::hittable::transform::Transform;
 use crate::hittable::xy_rect::XyRect;
 use crate::hittable::xz_rect::XzRect;
 use crate::hittable::yz_rect::YzRect;
+use crate::light::directional::DirectionalLight;
+use crate::light::environment::EnvironmentMap;
+use crate::light::point::PointLight;
+use crate::light::PunctualLight;
+use crate::light::sky::Sky;
+use crate::light::spot::SpotLight;
 use crate::material::dielectric::DielectricMaterial;
 use crate::material::diffuse_light::DiffuseLight;
+use crate::material::isotropic::IsotropicMaterial;
 use crate::material::lambertian::LambertianMaterial;
+use crate::material::Material;
 use crate::material::metal::MetalMaterial;
 use crate::scene::camera::{Camera, CameraCreateInfo};
-use crate::scene::Scene;
-use crate::scene::settings::{CameraSettings, RehndaSettings, SceneName};
+use crate::scene::obj_loader::load_obj;
+use crate::scene::{Background, Scene};
+use crate::scene::scene_description::{CameraDescription, EnvironmentDescription, KeyframeDescription, LightDescription, MaterialDescription, ObjectDescription, SceneDescription, SkyDescription, TextureDescription, TransformDescription};
+use crate::scene::settings::{AccelerationSettings, RehndaSettings};
 use crate::texture::checker::CheckerTexture;
 use crate::texture::image::ImageTexture;
 use crate::texture::noise::NoiseTexture;
 use crate::texture::solid::SolidTexture;
+use crate::texture::Texture;
+
+/// Render settings that affect how a scene description is built into a scene
+#[derive(Debug, Clone)]
+pub struct SceneOptions {
+    /// Width over height of the image, which the camera's field of view is stretched to
+    pub aspect_ratio: f32,
+    /// Lens diameter of the camera, zero for a pinhole camera with everything in focus
+    pub aperture: f32,
+    pub acceleration_settings: AccelerationSettings,
+    /// Seeds procedurally generated content like random spheres and noise textures
+    pub seed: u64,
+    /// Replaces the scene's background, environment map or sky
+    pub sky: Option<SkyDescription>,
+}
 
-const DEFAULT_BACKGROUND: ColorRgbF = ColorRgbF::new(0.7, 0.8, 1.0);
-
-pub fn load_scene(settings: &RehndaSettings) -> Scene {
-    match settings.scene {
-        SceneName::RandomSpheres => random_spheres_scene(&settings.camera_settings),
-        SceneName::ThreeSpheres => three_spheres_scene(&settings.camera_settings),
-        SceneName::Globe => globe_scene(&settings.camera_settings),
-        SceneName::LightsDemo => lights_demo_scene(&settings.camera_settings),
-        SceneName::CornellBox => cornell_box(&settings.camera_settings),
-        SceneName::CornellSmoke => cornell_smoke(&settings.camera_settings),
-        SceneName::CornellFeatureDemo => cornell_feature_demo(&settings.camera_settings),
-        _ => unimplemented!("Unsupported scene name!")
+impl SceneOptions {
+    /// Pinhole camera, default acceleration settings and seed zero
+    pub fn new(aspect_ratio: f32) -> SceneOptions {
+        SceneOptions {
+            aspect_ratio,
+            aperture: 0.0,
+            acceleration_settings: AccelerationSettings::default(),
+            seed: 0,
+            sky: None,
+        }
     }
 }
 
+pub fn load_scene(settings: &RehndaSettings) -> Result<Scene, RehndaError> {
+    let scene_description = load_scene_description(Path::new(&settings.scene_file))?;
+    build_scene(&scene_description, &settings.scene_options())
+}
 
-fn cornell_feature_demo(camera_settings: &CameraSettings) -> Scene {
-    let mut objects: Vec<Arc<dyn Hittable>> = Vec::new();
-
-    let checker_texture = Arc::new(CheckerTexture::new(0.1, Arc::new(SolidTexture::new(0.2, 0.3, 0.1)), Arc::new(SolidTexture::new(0.9, 0.9, 0.9))));
-    let ground_material = Arc::new(LambertianMaterial::new(checker_texture));
-    let red = Arc::new(LambertianMaterial::new_with_solid_color(&ColorRgbF::new(0.65, 0.05, 0.05)));
-    let white = Arc::new(LambertianMaterial::new_with_solid_color(&ColorRgbF::new(0.73, 0.73, 0.73)));
-    let green = Arc::new(LambertianMaterial::new_with_solid_color(&ColorRgbF::new(0.12, 0.45, 0.15)));
-    let light = Arc::new(DiffuseLight::new_solid_light(&ColorRgbF::new(15.0, 15.0, 15.0)));
-
-    // walls
-    objects.push(Arc::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 555.0, green)));
-    objects.push(Arc::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 0.0, red)));
-    objects.push(Arc::new(XzRect::new(213.0, 343.0, 227.0, 332.0, 554.0, light)));
-    // floor
-    objects.push(Arc::new(XzRect::new(0.0, 555.0, 0.0, 555.0, 0.0, ground_material)));
-    objects.push(Arc::new(XzRect::new(0.0, 555.0, 0.0, 555.0, 555.0, white.clone())));
-    objects.push(Arc::new(XyRect::new(0.0, 555.0, 0.0, 555.0, 555.0, white.clone())));
-
-    // left box
-    let box_1 = {
-        let metal_mat = Arc::new(MetalMaterial { albedo: ColorRgbF::new(1.0, 1.0, 1.0), fuzz: 0.01 });
-        let mut box_t: Arc<dyn Hittable> = Arc::new(BoxHittable::new(&Point3f::new(0.0, 0.0, 0.0), &Point3f::new(165.0, 330.0, 165.0), metal_mat));
-        box_t = Arc::new(RotateY::new(box_t, 15.0));
-        box_t = Arc::new(Translate::new(box_t, &Vec3f::new(265.0, 0.0, 295.0)));
-        box_t
-    };
-    objects.push(box_1);
+pub fn load_scene_description(path: &Path) -> Result<SceneDescription, RehndaError> {
+    let data = fs::read_to_string(path)
+        .map_err(|source| RehndaError::Read { path: path.to_path_buf(), source })?;
 
-    // right box
-    let box_2 = {
-        let mut box_t: Arc<dyn Hittable> = Arc::new(BoxHittable::new(&Point3f::new(0.0, 0.0, 0.0), &Point3f::new(165.0, 165.0, 165.0), white.clone()));
-        box_t = Arc::new(RotateY::new(box_t, -18.0));
-        box_t = Arc::new(Translate::new(box_t, &Vec3f::new(130.0, 0.0, 65.0)));
-        box_t
-    };
-    objects.push(box_2);
+    let scene_description: SceneDescription = ron::from_str(&data)
+        .map_err(|error| RehndaError::from_ron(path.to_path_buf(), error))?;
+    info!("Loaded scene {:?} with {} objects", path, scene_description.objects.len());
+    Ok(scene_description)
+}
 
-    // glass sphere on right box
-    let sphere_mat = Arc::new(DielectricMaterial { refractive_index: 1.5 });
-    let glass_sphere = Arc::new(Sphere {
-        centre: Point3f::new(212.5, 200.0, 147.5),
-        radius: 35.0,
-        material:sphere_mat,
-    });
-    objects.push(glass_sphere);
+/// Builds the scene, failing on undefined materials, textures that can't be loaded and objects
+/// with no area or volume
+pub fn build_scene(scene_description: &SceneDescription, options: &SceneOptions) -> Result<Scene, RehndaError> {
+    let acceleration_settings = &options.acceleration_settings;
+    // procedural content draws from one generator in a fixed order, so the same seed builds the same scene
+    let mut rng = seeded_rng(options.seed);
+    let materials: HashMap<&str, Arc<dyn Material>> = scene_description.materials.iter()
+        .map(|(name, material)| Ok((name.as_str(), build_material(material, &mut rng)?)))
+        .collect::<Result<_, RehndaError>>()?;
+
+    let mut lights: Vec<Arc<dyn Hittable>> = Vec::new();
+    let objects: Vec<Arc<dyn Hittable>> = scene_description.objects.iter()
+        .map(|object| build_object(object, &materials, acceleration_settings, &mut lights, &mut rng))
+        .collect::<Result<_, _>>()?;
+    if objects.is_empty() {
+        return Err(RehndaError::InvalidGeometry("the scene has no objects".to_string()));
+    }
 
-    // globe
-    let earth_texture = Arc::new(ImageTexture::new_from_image_file(Path::new("resources/earthmap.jpg")));
-    let earth_surface = Arc::new(LambertianMaterial::new(earth_texture));
-    let globe: Arc<dyn Hittable> = Arc::new(Sphere {
-        centre: Point3f::new(250.0, 70.0, 250.0),
-        radius: 70.0,
-        material: earth_surface,
-    });
-    objects.push(globe);
+    let (world, bvh_stats) = build_bvh(objects.as_slice(), 0.0, 1.0, acceleration_settings.bvh_builder, acceleration_settings.bvh_layout);
+    info!("World BVH ({:?} builder, {:?} layout): {}", acceleration_settings.bvh_builder, acceleration_settings.bvh_layout, bvh_stats);
+
+    let punctual_lights: Vec<Arc<dyn PunctualLight>> = scene_description.lights.iter()
+        .map(|light| build_light(light, &mut rng))
+        .collect::<Result<_, _>>()?;
+    info!("Found {} lights and {} punctual lights", lights.len(), punctual_lights.len());
+
+    Ok(Scene {
+        world,
+        lights,
+        punctual_lights,
+        camera: build_camera(&scene_description.camera, options),
+        background: build_background(scene_description, options)?,
+        bvh_stats,
+    })
+}
 
-    // fog floor
-    // let mut floor: Arc<dyn Hittable> = Arc::new(BoxHittable::new(&Point3f::new(0.0, 1.0, 0.0), &Point3f::new(555.0, 50.0, 555.0), white.clone()));
-    // floor = Arc::new(RotateY::new(floor, 45.0));
-    // objects.push(Arc::new(ConstantMedium::new_with_color(floor, 0.05, &ColorRgbF::new(0.8, 0.8, 0.8))));
+fn build_camera(camera_description: &CameraDescription, options: &SceneOptions) -> Camera {
+    Camera::new(&CameraCreateInfo {
+        look_from: camera_description.look_from,
+        look_at: camera_description.look_at,
+        up: camera_description.up,
+        vertical_fov_degrees: camera_description.vertical_fov_degrees,
+        aspect_ratio: options.aspect_ratio,
+        aperture: options.aperture,
+        focus_distance: camera_description.focus_distance,
+        time_0: camera_description.time_0,
+        time_1: camera_description.time_1,
+    })
+}
 
-    let cam_create_info = CameraCreateInfo {
-        look_from: Point3f::new(278.0, 278.0, -800.0),
-        look_at: Point3f::new(278.0, 278.0, 0.0),
-        up: Point3f::new(0.0, 1.0, 0.0),
-        vertical_fov_degrees: 40.0,
-        aspect_ratio: camera_settings.aspect_ratio(),
-        aperture: camera_settings.aperture,
-        focus_distance: 10.0,
-        time_0: 0.0,
-        time_1: 1.0,
-    };
-    let camera = Camera::new(&cam_create_info);
-    Scene {
-        world: Arc::new(BvhNode::new(objects.as_slice(), 0.0, 1.0)),
-        camera,
-        background: ColorRgbF::ZERO,
+fn build_background(scene_description: &SceneDescription, options: &SceneOptions) -> Result<Background, RehndaError> {
+    if let Some(sky) = &options.sky {
+        return Ok(Background::Environment(Arc::new(build_sky(sky)?)));
     }
+    Ok(match (&scene_description.environment, &scene_description.sky) {
+        (Some(_), Some(_)) => return Err(RehndaError::InvalidScene("a scene can have an environment map or a sky, not both".to_string())),
+        (Some(EnvironmentDescription { path, rotation, intensity }), None) => {
+            let environment = EnvironmentMap::load(Path::new(path), *rotation, *intensity)?;
+            info!("Loaded environment map {:?}", path);
+            Background::Environment(Arc::new(environment))
+        }
+        (None, Some(sky)) => Background::Environment(Arc::new(build_sky(sky)?)),
+        (None, None) => Background::Color(scene_description.background),
+    })
 }
 
-fn cornell_box(camera_settings: &CameraSettings) -> Scene {
-    let mut objects: Vec<Arc<dyn Hittable>> = Vec::new();
-
-    let red = Arc::new(LambertianMaterial::new_with_solid_color(&ColorRgbF::new(0.65, 0.05, 0.05)));
-    let white = Arc::new(LambertianMaterial::new_with_solid_color(&ColorRgbF::new(0.73, 0.73, 0.73)));
-    let green = Arc::new(LambertianMaterial::new_with_solid_color(&ColorRgbF::new(0.12, 0.45, 0.15)));
-    let light = Arc::new(DiffuseLight::new_solid_light(&ColorRgbF::new(15.0, 15.0, 15.0)));
-
-    objects.push(Arc::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 555.0, green)));
-    objects.push(Arc::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 0.0, red)));
-    objects.push(Arc::new(XzRect::new(213.0, 343.0, 227.0, 332.0, 554.0, light)));
-    objects.push(Arc::new(XzRect::new(0.0, 555.0, 0.0, 555.0, 0.0, white.clone())));
-    objects.push(Arc::new(XzRect::new(0.0, 555.0, 0.0, 555.0, 555.0, white.clone())));
-    objects.push(Arc::new(XyRect::new(0.0, 555.0, 0.0, 555.0, 555.0, white.clone())));
-
-    let box_1 = {
-        let mut box_t: Arc<dyn Hittable> = Arc::new(BoxHittable::new(&Point3f::new(0.0, 0.0, 0.0), &Point3f::new(165.0, 330.0, 165.0), white.clone()));
-        box_t = Arc::new(RotateY::new(box_t, 15.0));
-        box_t = Arc::new(Translate::new(box_t, &Vec3f::new(265.0, 0.0, 295.0)));
-        box_t
-    };
-    objects.push(box_1);
-
-    let box_2 = {
-        let mut box_t: Arc<dyn Hittable> = Arc::new(BoxHittable::new(&Point3f::new(0.0, 0.0, 0.0), &Point3f::new(165.0, 165.0, 165.0), white.clone()));
-        box_t = Arc::new(RotateY::new(box_t, -18.0));
-        box_t = Arc::new(Translate::new(box_t, &Vec3f::new(130.0, 0.0, 65.0)));
-        box_t
-    };
-    objects.push(box_2);
-
-    let cam_create_info = CameraCreateInfo {
-        look_from: Point3f::new(278.0, 278.0, -800.0),
-        look_at: Point3f::new(278.0, 278.0, 0.0),
-        up: Point3f::new(0.0, 1.0, 0.0),
-        vertical_fov_degrees: 40.0,
-        aspect_ratio: camera_settings.aspect_ratio(),
-        aperture: camera_settings.aperture,
-        focus_distance: 10.0,
-        time_0: 0.0,
-        time_1: 1.0,
-    };
-    let camera = Camera::new(&cam_create_info);
-    Scene {
-        world: Arc::new(BvhNode::new(objects.as_slice(), 0.0, 1.0)),
-        camera,
-        background: ColorRgbF::ZERO,
+fn build_sky(sky: &SkyDescription) -> Result<Sky, RehndaError> {
+    if !(0.0..=90.0).contains(&sky.sun_elevation) {
+        return Err(RehndaError::InvalidScene(format!("sun_elevation must be between 0 and 90 degrees, got {}", sky.sun_elevation)));
+    }
+    if !(2.0..=10.0).contains(&sky.turbidity) {
+        return Err(RehndaError::InvalidScene(format!("turbidity must be between 2 and 10, got {}", sky.turbidity)));
     }
+    if sky.sun_angular_diameter <= 0.0 || sky.sun_angular_diameter > 90.0 {
+        return Err(RehndaError::InvalidScene(format!("sun_angular_diameter must be above 0 and at most 90 degrees, got {}", sky.sun_angular_diameter)));
+    }
+    Ok(Sky::new(sky.sun_elevation, sky.sun_azimuth, sky.turbidity, sky.sun_angular_diameter, sky.intensity))
 }
 
+fn build_light(light_description: &LightDescription, rng: &mut Rng) -> Result<Arc<dyn PunctualLight>, RehndaError> {
+    Ok(match light_description {
+        LightDescription::Point { position, intensity } => Arc::new(PointLight { position: *position, intensity: *intensity }),
+        LightDescription::Spot { position, look_at, intensity, cone_angle, falloff_angle, projection, up } => {
+            let axis = *look_at - *position;
+            if axis.length_squared() == 0.0 {
+                return Err(RehndaError::InvalidScene(format!("spot light at {} can't look at its own position", position)));
+            }
+            if *cone_angle <= 0.0 || *cone_angle >= 90.0 {
+                return Err(RehndaError::InvalidScene(format!("spot light cone_angle must be above 0 and below 90 degrees, got {}", cone_angle)));
+            }
+            if !(0.0..=*cone_angle).contains(falloff_angle) {
+                return Err(RehndaError::InvalidScene(format!("spot light falloff_angle must be between 0 and the cone_angle of {} degrees, got {}", cone_angle, falloff_angle)));
+            }
+            let spot = SpotLight::new(*position, look_at, *intensity, *cone_angle, *falloff_angle);
+            match projection {
+                Some(texture) => {
+                    if axis.cross(*up).length_squared() == 0.0 {
+                        return Err(RehndaError::InvalidScene(format!("projecting spot light at {} needs an up vector that isn't along its axis", position)));
+                    }
+                    Arc::new(spot.with_projection(build_texture(texture, rng)?, up))
+                }
+                None => Arc::new(spot),
+            }
+        }
+        LightDescription::Directional { direction, irradiance } => {
+            if direction.length_squared() == 0.0 {
+                return Err(RehndaError::InvalidScene("directional light direction can't be zero".to_string()));
+            }
+            Arc::new(DirectionalLight::new(direction, *irradiance))
+        }
+    })
+}
 
-fn cornell_smoke(camera_settings: &CameraSettings) -> Scene {
-    let mut objects: Vec<Arc<dyn Hittable>> = Vec::new();
-
-    let red = Arc::new(LambertianMaterial::new_with_solid_color(&ColorRgbF::new(0.65, 0.05, 0.05)));
-    let white = Arc::new(LambertianMaterial::new_with_solid_color(&ColorRgbF::new(0.73, 0.73, 0.73)));
-    let green = Arc::new(LambertianMaterial::new_with_solid_color(&ColorRgbF::new(0.12, 0.45, 0.15)));
-    let light = Arc::new(DiffuseLight::new_solid_light(&ColorRgbF::new(15.0, 15.0, 15.0)));
-
-    objects.push(Arc::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 555.0, green)));
-    objects.push(Arc::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 0.0, red)));
-    objects.push(Arc::new(XzRect::new(213.0, 343.0, 227.0, 332.0, 554.0, light)));
-    objects.push(Arc::new(XzRect::new(0.0, 555.0, 0.0, 555.0, 0.0, white.clone())));
-    objects.push(Arc::new(XzRect::new(0.0, 555.0, 0.0, 555.0, 555.0, white.clone())));
-    objects.push(Arc::new(XyRect::new(0.0, 555.0, 0.0, 555.0, 555.0, white.clone())));
+fn build_texture(texture_description: &TextureDescription, rng: &mut Rng) -> Result<Arc<dyn Texture>, RehndaError> {
+    Ok(match texture_description {
+        TextureDescription::Solid(color) => Arc::new(SolidTexture { albedo: *color }),
+        TextureDescription::Checker { scale, even, odd } => Arc::new(CheckerTexture::new(*scale, build_texture(even, rng)?, build_texture(odd, rng)?)),
+        TextureDescription::Image(path) => Arc::new(ImageTexture::new_from_image_file(Path::new(path))?),
+        TextureDescription::Noise { scale } => Arc::new(NoiseTexture::new(*scale, rng)),
+    })
+}
 
-    let box_1 = {
-        let mut box_t: Arc<dyn Hittable> = Arc::new(BoxHittable::new(&Point3f::new(0.0, 0.0, 0.0), &Point3f::new(165.0, 330.0, 165.0), white.clone()));
-        box_t = Arc::new(RotateY::new(box_t, 15.0));
-        box_t = Arc::new(Translate::new(box_t, &Vec3f::new(265.0, 0.0, 295.0)));
-        box_t
-    };
-    objects.push(Arc::new(ConstantMedium::new_with_color(box_1, 0.01, &ColorRgbF::new(0.0, 0.0, 0.0))));
+fn build_material(material_description: &MaterialDescription, rng: &mut Rng) -> Result<Arc<dyn Material>, RehndaError> {
+    Ok(match material_description {
+        MaterialDescription::Lambertian(texture) => Arc::new(LambertianMaterial::new(build_texture(texture, rng)?)),
+        MaterialDescription::Metal { albedo, fuzz } => Arc::new(MetalMaterial { albedo: *albedo, fuzz: *fuzz }),
+        MaterialDescription::Dielectric { refractive_index } => Arc::new(DielectricMaterial { refractive_index: *refractive_index }),
+        MaterialDescription::DiffuseLight(texture) => Arc::new(DiffuseLight::new_with_texture(build_texture(texture, rng)?)),
+        MaterialDescription::Isotropic(texture) => Arc::new(IsotropicMaterial::new_with_texture(build_texture(texture, rng)?)),
+    })
+}
 
-    let box_2 = {
-        let mut box_t: Arc<dyn Hittable> = Arc::new(BoxHittable::new(&Point3f::new(0.0, 0.0, 0.0), &Point3f::new(165.0, 165.0, 165.0), white.clone()));
-        box_t = Arc::new(RotateY::new(box_t, -18.0));
-        box_t = Arc::new(Translate::new(box_t, &Vec3f::new(130.0, 0.0, 65.0)));
-        box_t
-    };
-    objects.push(Arc::new(ConstantMedium::new_with_color(box_2, 0.01, &ColorRgbF::new(1.0, 1.0, 1.0))));
+fn find_material(materials: &HashMap<&str, Arc<dyn Material>>, name: &str) -> Result<Arc<dyn Material>, RehndaError> {
+    materials.get(name)
+        .cloned()
+        .ok_or_else(|| RehndaError::UndefinedMaterial(name.to_string()))
+}
 
-    let cam_create_info = CameraCreateInfo {
-        look_from: Point3f::new(278.0, 278.0, -800.0),
-        look_at: Point3f::new(278.0, 278.0, 0.0),
-        up: Point3f::new(0.0, 1.0, 0.0),
-        vertical_fov_degrees: 40.0,
-        aspect_ratio: camera_settings.aspect_ratio(),
-        aperture: camera_settings.aperture,
-        focus_distance: 10.0,
-        time_0: 0.0,
-        time_1: 1.0,
-    };
-    let camera = Camera::new(&cam_create_info);
-    Scene {
-        world: Arc::new(BvhNode::new(objects.as_slice(), 0.0, 1.0)),
-        camera,
-        background: ColorRgbF::ZERO,
+/// A zero radius sphere has no surface. Negative radii are allowed, they turn the normals inwards
+/// for the inner surface of hollow glass spheres.
+fn validate_radius(radius: f32) -> Result<(), RehndaError> {
+    if radius == 0.0 || !radius.is_finite() {
+        return Err(RehndaError::InvalidGeometry(format!("sphere radius must be non-zero and finite, got {}", radius)));
     }
+    Ok(())
 }
 
-fn lights_demo_scene(camera_settings: &CameraSettings) -> Scene {
-    let mut objects: Vec<Arc<dyn Hittable>> = Vec::new();
-    let perlin_texture = Arc::new(NoiseTexture { scale: 4.0 });
-    objects.push(Arc::new(Sphere {
-        centre: Point3f::new(0.0, -1000.0, 0.0),
-        radius: 1000.0,
-        material: Arc::new(LambertianMaterial::new(perlin_texture.clone())),
-    }));
-    objects.push(Arc::new(Sphere {
-        centre: Point3f::new(0.0, 2.0, 0.0),
-        radius: 2.0,
-        material: Arc::new(LambertianMaterial::new(perlin_texture)),
-    }));
-
-    let diff_light = Arc::new(DiffuseLight::new_solid_light(&ColorRgbF::new(4.0, 4.0, 4.0)));
-    objects.push(Arc::new(XyRect::new(3.0, 5.0, 1.0, 3.0, -2.0, diff_light)));
-
-    let cam_create_info = CameraCreateInfo {
-        look_from: Point3f::new(26.0, 3.0, 6.0),
-        look_at: Point3f::new(0.0, 2.0, 0.0),
-        up: Point3f::new(0.0, 1.0, 0.0),
-        vertical_fov_degrees: 20.0,
-        aspect_ratio: camera_settings.aspect_ratio(),
-        aperture: camera_settings.aperture,
-        focus_distance: 10.0,
-        time_0: 0.0,
-        time_1: 1.0,
-    };
-    let camera = Camera::new(&cam_create_info);
-    Scene {
-        world: Arc::new(BvhNode::new(objects.as_slice(), 0.0, 1.0)),
-        camera,
-        background: ColorRgbF::ZERO,
+/// Rects and boxes span from their first to their second coordinate along each of `axes`, which
+/// has to be a positive distance
+fn validate_extent(object: &str, axes: &[(&str, f32, f32)]) -> Result<(), RehndaError> {
+    for (axis, start, end) in axes {
+        if start >= end {
+            return Err(RehndaError::InvalidGeometry(format!("{} spans {} to {} along {}, the end has to be greater than the start", object, start, end, axis)));
+        }
     }
+    Ok(())
 }
 
-fn globe_scene(camera_settings: &CameraSettings) -> Scene {
-    let earth_texture = Arc::new(ImageTexture::new_from_image_file(Path::new("resources/earthmap.jpg")));
-    let earth_surface = Arc::new(LambertianMaterial::new(earth_texture));
-    let globe: Arc<dyn Hittable> = Arc::new(Sphere {
-        centre: Point3f::ZERO,
-        radius: 2.0,
-        material: earth_surface,
-    });
-
-    let cam_create_info = CameraCreateInfo {
-        look_from: Point3f::new(13.0, 2.0, 3.0),
-        look_at: Point3f::splat(0.0),
-        up: Point3f::new(0.0, 1.0, 0.0),
-        vertical_fov_degrees: 20.0,
-        aspect_ratio: camera_settings.aspect_ratio(),
-        aperture: camera_settings.aperture,
-        focus_distance: 10.0,
-        time_0: 0.0,
-        time_1: 1.0,
-    };
-    let camera = Camera::new(&cam_create_info);
-    Scene {
-        world: Arc::new(BvhNode::new(slice::from_ref(&globe), 0.0, 1.0)),
-        camera,
-        background: DEFAULT_BACKGROUND,
+/// An animation needs a keyframe to hold the object at, and each keyframe has to be invertible:
+/// a rotation about a zero axis or a zero scale would leave every ray hitting NaN
+fn validate_keyframes(keyframes: &[KeyframeDescription]) -> Result<(), RehndaError> {
+    if keyframes.is_empty() {
+        return Err(RehndaError::InvalidScene("an animated object needs at least one keyframe".to_string()));
     }
+    for keyframe in keyframes {
+        if keyframe.rotation_axis.length_squared() == 0.0 {
+            return Err(RehndaError::InvalidScene(format!("keyframe at time {} has a zero rotation_axis", keyframe.time)));
+        }
+        if keyframe.scale.cmpeq(Vec3f::ZERO).any() {
+            return Err(RehndaError::InvalidScene(format!("keyframe at time {} has a zero component in its scale {}", keyframe.time, keyframe.scale)));
+        }
+    }
+    Ok(())
 }
 
-fn three_spheres_scene(camera_settings: &CameraSettings) -> Scene {
-    let mut objects: Vec<Arc<dyn Hittable>> = Vec::new();
+/// Each transform has to be invertible for the same reason as keyframes, a look at also needs a
+/// direction to look along and an up that isn't parallel to it
+fn validate_transforms(transforms: &[TransformDescription]) -> Result<(), RehndaError> {
+    for transform in transforms {
+        match transform {
+            TransformDescription::Translate(_) => {}
+            TransformDescription::Rotate { axis, .. } => if axis.length_squared() == 0.0 {
+                return Err(RehndaError::InvalidScene("Rotate transform has a zero axis".to_string()));
+            }
+            TransformDescription::Scale(scale) => if scale.cmpeq(Vec3f::ZERO).any() {
+                return Err(RehndaError::InvalidScene(format!("Scale transform has a zero component in {}", scale)));
+            }
+            TransformDescription::UniformScale(scale) => if *scale == 0.0 {
+                return Err(RehndaError::InvalidScene("UniformScale transform has a scale of zero".to_string()));
+            }
+            TransformDescription::LookAt { from, to, up } => if from == to {
+                return Err(RehndaError::InvalidScene(format!("LookAt transform has the same from and to of {}", from)));
+            } else if (*to - *from).cross(*up).length_squared() == 0.0 {
+                return Err(RehndaError::InvalidScene(format!("LookAt transform has an up of {} parallel to the direction from {} to {}", up, from, to)));
+            }
+        }
+    }
+    Ok(())
+}
 
-    let checker_texture = Arc::new(CheckerTexture::new(10.0, Arc::new(SolidTexture::new(0.2, 0.3, 0.1)), Arc::new(SolidTexture::new(0.9, 0.9, 0.9))));
-    let ground_material = Arc::new(LambertianMaterial::new(checker_texture));
-    let centre_material = Arc::new(LambertianMaterial::new_with_solid_color(&ColorRgbF::new(0.7, 0.3, 0.3)));
-    let left_material = Arc::new(DielectricMaterial { refractive_index: 1.5 });
-    let right_material = Arc::new(MetalMaterial { albedo: ColorRgbF::new(0.8, 0.6, 0.2), fuzz: 0.7});
+/// Builds the hittable for an object, adding any emissive parts of it to `lights`
+fn build_object(object_description: &ObjectDescription, materials: &HashMap<&str, Arc<dyn Material>>, acceleration_settings: &AccelerationSettings, lights: &mut Vec<Arc<dyn Hittable>>, rng: &mut Rng) -> Result<Arc<dyn Hittable>, RehndaError> {
+    Ok(match object_description {
+        ObjectDescription::Sphere { centre, radius, material } => {
+            validate_radius(*radius)?;
+            let material = find_material(materials, material)?;
+            add_if_emissive(Arc::new(Sphere {
+                centre: *centre,
+                radius: *radius,
+                material: material.clone(),
+            }), &material, lights)
+        }
+        ObjectDescription::MovingSphere { centre_0, centre_1, time_0, time_1, radius, material } => {
+            validate_radius(*radius)?;
+            // the centre is interpolated over the time between the two, which has to be non-zero
+            if time_0 == time_1 {
+                return Err(RehndaError::InvalidScene(format!("moving sphere has the same time_0 and time_1 of {}, use a Sphere if it doesn't move", time_0)));
+            }
+            let material = find_material(materials, material)?;
+            add_if_emissive(Arc::new(MovingSphere {
+                centre_0: *centre_0,
+                centre_1: *centre_1,
+                time_0: *time_0,
+                time_1: *time_1,
+                radius: *radius,
+                material: material.clone(),
+            }), &material, lights)
+        }
+        ObjectDescription::XyRect { x0, x1, y0, y1, k, material } => {
+            validate_extent("XyRect", &[("x", *x0, *x1), ("y", *y0, *y1)])?;
+            let material = find_material(materials, material)?;
+            add_if_emissive(Arc::new(XyRect::new(*x0, *x1, *y0, *y1, *k, material.clone())), &material, lights)
+        }
+        ObjectDescription::XzRect { x0, x1, z0, z1, k, material } => {
+            validate_extent("XzRect", &[("x", *x0, *x1), ("z", *z0, *z1)])?;
+            let material = find_material(materials, material)?;
+            add_if_emissive(Arc::new(XzRect::new(*x0, *x1, *z0, *z1, *k, material.clone())), &material, lights)
+        }
+        ObjectDescription::YzRect { y0, y1, z0, z1, k, material } => {
+            validate_extent("YzRect", &[("y", *y0, *y1), ("z", *z0, *z1)])?;
+            let material = find_material(materials, material)?;
+            add_if_emissive(Arc::new(YzRect::new(*y0, *y1, *z0, *z1, *k, material.clone())), &material, lights)
+        }
+        ObjectDescription::Box { min_corner, max_corner, material } => {
+            validate_extent("Box", &[("x", min_corner.x, max_corner.x), ("y", min_corner.y, max_corner.y), ("z", min_corner.z, max_corner.z)])?;
+            let material = find_material(materials, material)?;
+            add_if_emissive(Arc::new(BoxHittable::new(min_corner, max_corner, material.clone())), &material, lights)
+        }
+        ObjectDescription::RotateY { angle, object } => {
+            let lights_start = lights.len();
+            let transform = Transform::new(build_object(object, materials, acceleration_settings, lights, rng)?).rotate(&Vec3f::Y, *angle);
+            build_transform(transform, &mut lights[lights_start..])
+        }
+        ObjectDescription::Translate { offset, object } => {
+            let lights_start = lights.len();
+            let transform = Transform::new(build_object(object, materials, acceleration_settings, lights, rng)?).translate(offset);
+            build_transform(transform, &mut lights[lights_start..])
+        }
+        ObjectDescription::Transform { transforms, object } => {
+            validate_transforms(transforms)?;
+            let lights_start = lights.len();
+            let transform = transforms.iter().fold(Transform::new(build_object(object, materials, acceleration_settings, lights, rng)?), apply_transform);
+            build_transform(transform, &mut lights[lights_start..])
+        }
+        ObjectDescription::Animated { keyframes, interpolation, object } => {
+            validate_keyframes(keyframes)?;
+            let lights_start = lights.len();
+            let animated_transform = AnimatedTransform::new(
+                build_object(object, materials, acceleration_settings, lights, rng)?,
+                keyframes.iter().map(build_keyframe).collect(),
+                *interpolation,
+            );
+            for light in &mut lights[lights_start..] {
+                *light = Arc::new(animated_transform.with_hittable(light.clone()));
+            }
+            Arc::new(animated_transform)
+        }
+        ObjectDescription::Mesh { path, material, group_materials } => {
+            let mesh = load_obj(Path::new(path), acceleration_settings, |group| {
+                // groups listed in `group_materials` have to name a scene material, unlisted groups
+                // only use a scene material of the same name if there is one
+                let material_name = match group {
+                    Some(group) => group_materials.get(group).map_or_else(
+                        || if materials.contains_key(group) { group } else { material.as_str() },
+                        String::as_str,
+                    ),
+                    None => material,
+                };
+                find_material(materials, material_name)
+            })?;
+            lights.extend(mesh.light().cloned());
+            Arc::new(mesh)
+        }
+        ObjectDescription::ConstantMedium { boundary, density, texture } => {
+            // the boundary only shapes the volume and is never rendered, so any lights in it are dropped
+            let boundary = build_object(boundary, materials, acceleration_settings, &mut Vec::new(), rng)?;
+            Arc::new(ConstantMedium::new_with_texture(boundary, *density, build_texture(texture, rng)?))
+        }
+        ObjectDescription::RandomSpheres { half_extent, bounce_height } => random_spheres(*half_extent, *bounce_height, acceleration_settings, rng),
+    })
+}
 
-    objects.push(Arc::new(Sphere {
-        centre: Point3f::new(0.0, -100.5, -1.0),
-        radius: 100.0,
-        material: ground_material,
-    }));
+fn add_if_emissive(object: Arc<dyn Hittable>, material: &Arc<dyn Material>, lights: &mut Vec<Arc<dyn Hittable>>) -> Arc<dyn Hittable> {
+    if material.is_emissive() {
+        lights.push(object.clone());
+    }
+    object
+}
 
-    objects.push(Arc::new(Sphere {
-        centre: Point3f::new(0.0, 0.0, -1.0),
-        radius: 0.5,
-        material: centre_material,
-    }));
-    objects.push(Arc::new(Sphere {
-        centre: Point3f::new(-1.0, 0.0, -1.0),
-        radius: 0.5,
-        material: left_material.clone(),
-    }));
-    objects.push(Arc::new(Sphere {
-        centre: Point3f::new(1.0, 0.0, -1.0),
-        radius: 0.5,
-        material: right_material,
-    }));
+/// `object_lights` were added while building the transformed object so are in its object space,
+/// they are moved into world space with the same transform
+fn build_transform(transform: Transform, object_lights: &mut [Arc<dyn Hittable>]) -> Arc<dyn Hittable> {
+    for light in object_lights.iter_mut() {
+        *light = Arc::new(transform.with_hittable(light.clone()));
+    }
+    Arc::new(transform)
+}
 
-    let cam_create_info = CameraCreateInfo {
-        look_from: Point3f::new(0.0, 0.0, 0.0),
-        look_at: Point3f::new(0.0, 0.0, -1.0),
-        up: Point3f::new(0.0, 1.0, 0.0),
-        vertical_fov_degrees: 100.0,
-        aspect_ratio: camera_settings.aspect_ratio(),
-        aperture: camera_settings.aperture,
-        focus_distance: 1.0,
-        time_0: 0.0,
-        time_1: 1.0,
-    };
-    let camera = Camera::new(&cam_create_info);
+fn apply_transform(transform: Transform, transform_description: &TransformDescription) -> Transform {
+    match transform_description {
+        TransformDescription::Translate(offset) => transform.translate(offset),
+        TransformDescription::Rotate { axis, angle } => transform.rotate(axis, *angle),
+        TransformDescription::Scale(scale) => transform.scale(scale),
+        TransformDescription::UniformScale(scale) => transform.scale(&Vec3f::splat(*scale)),
+        TransformDescription::LookAt { from, to, up } => transform.look_at(from, to, up),
+    }
+}
 
-    Scene {
-        world: Arc::new(BvhNode::new(objects.as_slice(), 0.0, 1.0)),
-        camera,
-        background: DEFAULT_BACKGROUND,
+fn build_keyframe(keyframe_description: &KeyframeDescription) -> Keyframe {
+    Keyframe {
+        time: keyframe_description.time,
+        translation: keyframe_description.translation,
+        rotation: Quat::from_axis_angle(Vec3::from(keyframe_description.rotation_axis.unit_vector()), keyframe_description.rotation_angle.to_radians()),
+        scale: keyframe_description.scale,
     }
 }
 
-fn random_spheres_scene(camera_settings: &CameraSettings) -> Scene {
+fn random_spheres(half_extent: i32, bounce_height: f32, acceleration_settings: &AccelerationSettings, rng: &mut Rng) -> Arc<dyn Hittable> {
     let mut objects: Vec<Arc<dyn Hittable>> = Vec::new();
-    let ground_material = Arc::new(LambertianMaterial {
-        texture: Arc::new(NoiseTexture { scale: 4.0 })
-    });
-    objects.push(Arc::new(Sphere {
-        centre: Point3f::new(0.0, -1000.0, 0.0),
-        radius: 1000.0,
-        material: ground_material,
-    }));
-    let num_random_objects = 11;
-    for a in -num_random_objects..num_random_objects {
-        for b in -num_random_objects..num_random_objects {
-            let choose_mat = random_in_range(0.0, 1.0);
-            let centre = Point3f::new(a as f32 + 0.9 * random_in_range(0.0, 1.0), 0.2, b as f32 + 0.9 * random_in_range(0.0, 1.0));
+    for a in -half_extent..half_extent {
+        for b in -half_extent..half_extent {
+            let choose_mat = random_in_range(rng, 0.0, 1.0);
+            let centre = Point3f::new(a as f32 + 0.9 * random_in_range(rng, 0.0, 1.0), 0.2, b as f32 + 0.9 * random_in_range(rng, 0.0, 1.0));
             if (centre - Point3f::new(4.0, 0.2, 0.0)).length() > 0.9 {
-                if choose_mat < 0.8 {
-                    let albedo: ColorRgbF = ColorRgbF::random_vec_in_range(0.0, 1.0) * ColorRgbF::random_vec_in_range(0.0, 1.0);
-                    let material = Arc::new(LambertianMaterial::new_with_solid_color(&albedo));
-                    objects.push(Arc::new(Sphere {
-                        centre,
+                let sphere: Arc<dyn Hittable> = if choose_mat < 0.8 {
+                    let albedo: ColorRgbF = ColorRgbF::random_vec_in_range(rng, 0.0, 1.0) * ColorRgbF::random_vec_in_range(rng, 0.0, 1.0);
+                    Arc::new(MovingSphere {
+                        centre_0: centre,
+                        centre_1: centre + Vec3f::new(0.0, random_in_range(rng, 0.0, bounce_height), 0.0),
+                        time_0: 0.0,
+                        time_1: 1.0,
                         radius: 0.2,
-                        material,
-                    }));
+                        material: Arc::new(LambertianMaterial::new_with_solid_color(&albedo)),
+                    })
                 } else if choose_mat < 0.95 {
-                    let albedo: ColorRgbF = ColorRgbF::random_vec_in_range(0.5, 1.0);
-                    let fuzz = random_in_range(0.0, 0.5);
-                    let material = Arc::new(MetalMaterial {
-                        albedo,
-                        fuzz,
-                    });
-                    objects.push(Arc::new(Sphere {
+                    let albedo: ColorRgbF = ColorRgbF::random_vec_in_range(rng, 0.5, 1.0);
+                    let fuzz = random_in_range(rng, 0.0, 0.5);
+                    Arc::new(Sphere {
                         centre,
                         radius: 0.2,
-                        material,
-                    }));
+                        material: Arc::new(MetalMaterial {
+                            albedo,
+                            fuzz,
+                        }),
+                    })
                 } else {
-                    let material = Arc::new(DielectricMaterial {
-                        refractive_index: 1.5,
-                    });
-                    objects.push(Arc::new(Sphere {
+                    Arc::new(Sphere {
                         centre,
                         radius: 0.2,
-                        material,
-                    }));
-                }
+                        material: Arc::new(DielectricMaterial {
+                            refractive_index: 1.5,
+                        }),
+                    })
+                };
+                objects.push(sphere);
             }
         }
     }
 
-    objects.push(Arc::new(Sphere {
-        centre: Point3f::new(0.0, 1.0, 0.0),
-        radius: 1.0,
-        material: Arc::new(DielectricMaterial { refractive_index: 1.5}),
-    }));
-
-    objects.push(Arc::new(Sphere {
-        centre: Point3f::new(-4.0, 1.0, 0.0),
-        radius: 1.0,
-        material: Arc::new(LambertianMaterial::new_with_solid_color(&ColorRgbF::new(0.4, 0.2, 0.1))),
-    }));
-
-    objects.push(Arc::new(Sphere {
-        centre: Point3f::new(4.0, 1.0, 0.0),
-        radius: 1.0,
-        material: Arc::new(MetalMaterial {
-            albedo: ColorRgbF::new(0.7, 0.6, 0.5),
-            fuzz: 0.0,
-        }),
-    }));
-
-
-    let cam_create_info = CameraCreateInfo {
-        look_from: Point3f::new(13.0, 2.0, 3.0),
-        look_at: Point3f::splat(0.0),
-        up: Point3f::new(0.0, 1.0, 0.0),
-        vertical_fov_degrees: 20.0,
-        aspect_ratio: camera_settings.aspect_ratio(),
-        aperture: camera_settings.aperture,
-        focus_distance: 10.0,
-        time_0: 0.0,
-        time_1: 1.0,
-    };
-    let camera = Camera::new(&cam_create_info);
-    Scene {
-        world: Arc::new(BvhNode::new(objects.as_slice(), 0.0, 1.0)),
-        camera,
-        background: DEFAULT_BACKGROUND,
-    }
+    build_bvh(objects.as_slice(), 0.0, 1.0, acceleration_settings.bvh_builder, acceleration_settings.bvh_layout).0
 }
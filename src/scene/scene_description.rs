@@ -0,0 +1,294 @@
+use std::collections::{BTreeMap, HashMap};
+use serde::Deserialize;
+use crate::core::color::ColorRgbF;
+use crate::core::rehnda_math::{Point3f, Vec3f};
+use crate::hittable::animated_transform::Interpolation;
+use crate::light::sky::SUN_ANGULAR_DIAMETER;
+
+/// Data driven description of a scene, loaded from a RON scene file.
+///
+/// Materials are declared once by name and referenced by name from objects so they can be
+/// shared between primitives the same way the built in scenes used to share `Arc` materials.
+#[derive(Clone, Deserialize, Debug)]
+pub struct SceneDescription {
+    pub camera: CameraDescription,
+    #[serde(default)]
+    pub background: ColorRgbF,
+    /// Lights the scene from every direction and replaces `background` for rays escaping the world
+    #[serde(default)]
+    pub environment: Option<EnvironmentDescription>,
+    /// Daylight sky and sun, an alternative to `environment`
+    #[serde(default)]
+    pub sky: Option<SkyDescription>,
+    /// Point, spot and directional lights, in addition to any emissive objects
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+    #[serde(default)]
+    pub materials: BTreeMap<String, MaterialDescription>,
+    pub objects: Vec<ObjectDescription>,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct CameraDescription {
+    pub look_from: Point3f,
+    pub look_at: Point3f,
+    #[serde(default = "default_up")]
+    pub up: Vec3f,
+    pub vertical_fov_degrees: f32,
+    pub focus_distance: f32,
+    #[serde(default)]
+    pub time_0: f32,
+    #[serde(default = "default_time_1")]
+    pub time_1: f32,
+}
+
+/// Latitude-longitude environment map, see [crate::light::environment::EnvironmentMap]
+#[derive(Clone, Deserialize, Debug)]
+pub struct EnvironmentDescription {
+    /// Linear Radiance (`.hdr`) or OpenEXR (`.exr`) image
+    pub path: String,
+    /// Degrees to turn the map about the up (y) axis
+    #[serde(default)]
+    pub rotation: f32,
+    /// Scales the radiance of the map
+    #[serde(default = "default_intensity")]
+    pub intensity: f32,
+}
+
+/// Analytic daylight sky and sun, see [crate::light::sky::Sky]
+#[derive(Clone, Deserialize, Debug)]
+pub struct SkyDescription {
+    /// Degrees of the sun above the horizon, from 0 to 90
+    #[serde(default = "default_sun_elevation")]
+    pub sun_elevation: f32,
+    /// Degrees the sun is turned about the up (y) axis, from -z towards +x
+    #[serde(default)]
+    pub sun_azimuth: f32,
+    /// Haziness of the atmosphere, from 2 for a clear sky to 10 for a hazy one
+    #[serde(default = "default_turbidity")]
+    pub turbidity: f32,
+    /// Degrees across the sun's disc, larger suns cast softer shadows
+    #[serde(default = "default_sun_angular_diameter")]
+    pub sun_angular_diameter: f32,
+    /// Scales the radiance of the sky and sun
+    #[serde(default = "default_intensity")]
+    pub intensity: f32,
+}
+
+/// Light from a single point or direction, which rays can't hit, so it's only seen by the
+/// `NextEventEstimation` and `MultipleImportanceSampling` integrators and never in reflections of
+/// perfect mirrors and glass
+#[derive(Clone, Deserialize, Debug)]
+pub enum LightDescription {
+    /// Shines equally in every direction, `intensity` is the irradiance it gives a surface one
+    /// unit away, falling off with the square of the distance
+    Point {
+        position: Point3f,
+        intensity: ColorRgbF,
+    },
+    /// Point light shining towards `look_at` in a cone `cone_angle` degrees either side of its
+    /// axis (below 90), fading out over the outer `falloff_angle` degrees of the cone
+    Spot {
+        position: Point3f,
+        look_at: Point3f,
+        intensity: ColorRgbF,
+        cone_angle: f32,
+        #[serde(default)]
+        falloff_angle: f32,
+        /// Texture projected over the cone like a slide, with `up` towards the top of the image
+        #[serde(default)]
+        projection: Option<TextureDescription>,
+        #[serde(default = "default_up")]
+        up: Vec3f,
+    },
+    /// Parallel light travelling along `direction` from infinitely far away, giving `irradiance`
+    /// to surfaces facing it
+    Directional {
+        direction: Vec3f,
+        irradiance: ColorRgbF,
+    },
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub enum TextureDescription {
+    Solid(ColorRgbF),
+    Checker {
+        scale: f32,
+        even: Box<TextureDescription>,
+        odd: Box<TextureDescription>,
+    },
+    Image(String),
+    Noise {
+        scale: f32,
+    },
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub enum MaterialDescription {
+    Lambertian(TextureDescription),
+    Metal {
+        albedo: ColorRgbF,
+        fuzz: f32,
+    },
+    Dielectric {
+        refractive_index: f32,
+    },
+    DiffuseLight(TextureDescription),
+    Isotropic(TextureDescription),
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub enum ObjectDescription {
+    Sphere {
+        centre: Point3f,
+        radius: f32,
+        material: String,
+    },
+    /// Sphere moving linearly from `centre_0` at `time_0` to `centre_1` at `time_1`
+    MovingSphere {
+        centre_0: Point3f,
+        centre_1: Point3f,
+        #[serde(default)]
+        time_0: f32,
+        #[serde(default = "default_time_1")]
+        time_1: f32,
+        radius: f32,
+        material: String,
+    },
+    XyRect {
+        x0: f32,
+        x1: f32,
+        y0: f32,
+        y1: f32,
+        k: f32,
+        material: String,
+    },
+    XzRect {
+        x0: f32,
+        x1: f32,
+        z0: f32,
+        z1: f32,
+        k: f32,
+        material: String,
+    },
+    YzRect {
+        y0: f32,
+        y1: f32,
+        z0: f32,
+        z1: f32,
+        k: f32,
+        material: String,
+    },
+    Box {
+        min_corner: Point3f,
+        max_corner: Point3f,
+        material: String,
+    },
+    /// Shorthand for a [ObjectDescription::Transform] with a single rotation about the y axis
+    RotateY {
+        angle: f32,
+        object: Box<ObjectDescription>,
+    },
+    /// Shorthand for a [ObjectDescription::Transform] with a single translation
+    Translate {
+        offset: Vec3f,
+        object: Box<ObjectDescription>,
+    },
+    /// Applies each of `transforms` to `object` in the order listed
+    Transform {
+        transforms: Vec<TransformDescription>,
+        object: Box<ObjectDescription>,
+    },
+    /// Moves `object` between keyframes over time, so it is motion blurred across the camera's shutter
+    Animated {
+        keyframes: Vec<KeyframeDescription>,
+        #[serde(default)]
+        interpolation: Interpolation,
+        object: Box<ObjectDescription>,
+    },
+    /// Triangle mesh loaded from a Wavefront OBJ file. Faces are given `material` unless their
+    /// `usemtl` group is listed in `group_materials` (OBJ material name to scene material name),
+    /// or the group name itself matches a scene material.
+    Mesh {
+        path: String,
+        material: String,
+        #[serde(default)]
+        group_materials: HashMap<String, String>,
+    },
+    ConstantMedium {
+        boundary: Box<ObjectDescription>,
+        density: f32,
+        texture: TextureDescription,
+    },
+    /// Procedural grid of small randomly placed and coloured spheres, as used by the cover
+    /// image of Ray Tracing in One Weekend and The Next Week. Spheres are placed in `-half_extent..half_extent`
+    /// on both the x and z axis.
+    /// The small diffuse spheres bounce upwards by a random height of up to `bounce_height` over
+    /// the shutter interval.
+    RandomSpheres {
+        half_extent: i32,
+        #[serde(default)]
+        bounce_height: f32,
+    },
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub enum TransformDescription {
+    Translate(Vec3f),
+    /// Rotation of `angle` degrees about `axis`
+    Rotate {
+        axis: Vec3f,
+        angle: f32,
+    },
+    Scale(Vec3f),
+    UniformScale(f32),
+    LookAt {
+        from: Point3f,
+        to: Point3f,
+        #[serde(default = "default_up")]
+        up: Vec3f,
+    },
+}
+
+/// Keyframe of an animated object, the object is scaled, then rotated `rotation_angle` degrees
+/// about `rotation_axis`, then translated
+#[derive(Clone, Deserialize, Debug)]
+pub struct KeyframeDescription {
+    pub time: f32,
+    #[serde(default)]
+    pub translation: Vec3f,
+    #[serde(default = "default_up")]
+    pub rotation_axis: Vec3f,
+    #[serde(default)]
+    pub rotation_angle: f32,
+    #[serde(default = "default_scale")]
+    pub scale: Vec3f,
+}
+
+fn default_up() -> Vec3f {
+    Vec3f::new(0.0, 1.0, 0.0)
+}
+
+fn default_scale() -> Vec3f {
+    Vec3f::ONE
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+fn default_sun_angular_diameter() -> f32 {
+    SUN_ANGULAR_DIAMETER
+}
+
+fn default_sun_elevation() -> f32 {
+    45.0
+}
+
+fn default_turbidity() -> f32 {
+    3.0
+}
+
+fn default_time_1() -> f32 {
+    1.0
+}
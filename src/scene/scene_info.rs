@@ -0,0 +1,50 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use log::info;
+use crate::error::RehndaError;
+use crate::scene::scene_builder::{build_scene, load_scene_description};
+use crate::scene::scene_description::ObjectDescription;
+use crate::scene::settings::RehndaSettings;
+
+/// Logs statistics about the scene of `settings`: what it's made of, its size and how well its
+/// BVH is built
+pub fn print_scene_info(settings: &RehndaSettings) -> Result<(), RehndaError> {
+    let scene_description = load_scene_description(Path::new(&settings.scene_file))?;
+    let scene = build_scene(&scene_description, &settings.scene_options())?;
+
+    let (image_width, image_height) = settings.resolution();
+    info!("Resolution: {}x{}, {} samples per pixel", image_width, image_height, settings.num_samples);
+    let mut object_counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for object in &scene_description.objects {
+        *object_counts.entry(object_kind(object)).or_default() += 1;
+    }
+    let object_counts: Vec<String> = object_counts.iter().map(|(kind, count)| format!("{} {}", count, kind)).collect();
+    info!("Objects: {}", object_counts.join(", "));
+    info!("Materials: {}, lights: {}, punctual lights: {}", scene_description.materials.len(), scene.lights.len(), scene.punctual_lights.len());
+    info!("BVH: {}", scene.bvh_stats);
+    if let Some(bounds) = scene.world.bounding_box(0.0, 1.0) {
+        info!("World bounds: {} to {}", bounds.min_corner, bounds.max_corner);
+    }
+    let camera = &scene_description.camera;
+    info!("Camera: from {} looking at {}, {} degree vertical field of view", camera.look_from, camera.look_at, camera.vertical_fov_degrees);
+    Ok(())
+}
+
+/// Kind of primitive an object is made of, looking through transforms
+fn object_kind(object: &ObjectDescription) -> &'static str {
+    match object {
+        ObjectDescription::Sphere { .. } => "Sphere",
+        ObjectDescription::MovingSphere { .. } => "MovingSphere",
+        ObjectDescription::XyRect { .. } => "XyRect",
+        ObjectDescription::XzRect { .. } => "XzRect",
+        ObjectDescription::YzRect { .. } => "YzRect",
+        ObjectDescription::Box { .. } => "Box",
+        ObjectDescription::RotateY { object, .. }
+        | ObjectDescription::Translate { object, .. }
+        | ObjectDescription::Transform { object, .. }
+        | ObjectDescription::Animated { object, .. } => object_kind(object),
+        ObjectDescription::Mesh { .. } => "Mesh",
+        ObjectDescription::ConstantMedium { .. } => "ConstantMedium",
+        ObjectDescription::RandomSpheres { .. } => "RandomSpheres",
+    }
+}
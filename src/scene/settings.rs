@@ -1,34 +1,75 @@
-use std::fs::File;
-use std::io::Read;
-use std::num::NonZeroUsize;
+use std::fs;
 use std::path::Path;
-use std::thread;
 use log::info;
+use ron::extensions::Extensions;
 use serde::Deserialize;
+use crate::acceleration::bvh::{BvhBuilder, BvhLayout};
+use crate::aggregator::{AggregationConfig, available_threads};
+use crate::aggregator::adaptive_sampling::AdaptiveSampling;
+use crate::aggregator::crop_window::CropWindow;
+use crate::aggregator::integrator::Integrator;
+use crate::aggregator::path_termination::{PathSettings, PathTermination};
+use crate::aggregator::progressive::ProgressiveRendering;
+use crate::core::rehnda_math::{hash_bytes, hash_u64};
+use crate::error::RehndaError;
+use crate::image::filter::ReconstructionFilter;
+use crate::image::tone_mapping::DisplaySettings;
+use crate::scene::scene_builder::SceneOptions;
+use crate::scene::scene_description::SkyDescription;
+use crate::scene::settings_override::apply_override;
+use crate::sampler::Sampler;
 
 #[derive(Clone, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct RehndaSettings {
-    pub scene: SceneName,
+    pub scene_file: String,
     pub output_file: Option<String>,
-    pub max_depth: usize,
+    /// Hard limit on the number of rays in a path, optional unless `path_termination` is `MaxDepth`
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    #[serde(default)]
+    pub path_termination: PathTermination,
+    #[serde(default)]
+    pub integrator: Integrator,
+    #[serde(default)]
+    pub sampler: Sampler,
+    #[serde(default)]
+    pub filter: ReconstructionFilter,
+    /// Width in pixels at full resolution, the height follows from the aspect ratio
     pub image_width: usize,
+    /// Factor the resolution is scaled by, e.g. 0.25 for a quick low resolution preview
+    #[serde(default = "default_preview_scale")]
+    pub preview_scale: f32,
+    /// Renders only part of the image
+    #[serde(default)]
+    pub crop_window: Option<CropWindow>,
+    /// Samples per pixel, or the most a pixel can receive with adaptive sampling
     pub num_samples: usize,
+    /// Stops sampling pixels once they have converged
+    #[serde(default)]
+    pub adaptive_sampling: Option<AdaptiveSampling>,
+    /// Renders in passes of increasing sample counts, writing the output file along the way
+    #[serde(default)]
+    pub progressive: Option<ProgressiveRendering>,
+    /// Seeds all randomness, so renders of the same settings are identical whatever the thread count
+    #[serde(default)]
+    pub seed: u64,
     pub camera_settings: CameraSettings,
+    #[serde(default)]
+    pub acceleration_settings: AccelerationSettings,
+    #[serde(default)]
+    pub display_settings: DisplaySettings,
+    /// Daylight sky and sun replacing the scene's background, environment map or sky, e.g. to
+    /// relight an outdoor scene with `--set sky.sun_elevation=10`
+    #[serde(default)]
+    pub sky: Option<SkyDescription>,
+    /// Where to save the raw sample sums whenever the image is written, for resuming or merging renders
+    #[serde(default)]
+    pub checkpoint_file: Option<String>,
     num_threads: Option<usize>,
 }
 
 
-#[derive(Clone, Deserialize, Debug)]
-pub enum SceneName {
-    ThreeSpheres,
-    RandomSpheres,
-    Globe,
-    LightsDemo,
-    CornellBox,
-    CornellSmoke,
-    CornellFeatureDemo,
-}
-
 #[derive(Clone, Deserialize, Debug)]
 struct AspectRatioSettings {
     width: f32,
@@ -41,6 +82,14 @@ pub struct CameraSettings {
     pub aperture: f32,
 }
 
+#[derive(Clone, Deserialize, Debug, Default)]
+pub struct AccelerationSettings {
+    #[serde(default)]
+    pub bvh_builder: BvhBuilder,
+    #[serde(default)]
+    pub bvh_layout: BvhLayout,
+}
+
 impl CameraSettings {
     pub fn aspect_ratio(&self) -> f32 {
         self.aspect_ratio.width / self.aspect_ratio.height
@@ -48,29 +97,95 @@ impl CameraSettings {
 }
 
 impl RehndaSettings {
-    pub fn from_file(path: &Path) -> RehndaSettings {
-        let mut file = File::open(path).expect("File not found!");
-        let mut data = String::new();
-        file.read_to_string(&mut data).expect("Error reading settings file");
+    /// Loads the settings file with each of the `key=value` `overrides` applied in order, see
+    /// [apply_override]. `Some` can be left out around optional values.
+    pub fn from_file(path: &Path, overrides: &[String]) -> Result<RehndaSettings, RehndaError> {
+        let file_data = fs::read_to_string(path)
+            .map_err(|source| RehndaError::Read { path: path.to_path_buf(), source })?;
+        // the document after each override, to find the one that broke it if it doesn't parse
+        let mut documents = vec![file_data];
+        for assignment in overrides {
+            let data = apply_override(documents.last().unwrap(), assignment)
+                .map_err(|message| RehndaError::InvalidOverride { assignment: assignment.clone(), message })?;
+            documents.push(data);
+        }
 
-        let settings = ron::from_str(&data).unwrap();
+        let parse = |data: &str| ron::Options::default()
+            .with_default_extension(Extensions::IMPLICIT_SOME)
+            .from_str::<RehndaSettings>(data);
+        let settings = parse(documents.last().unwrap()).map_err(|error| {
+            // positions in an edited document aren't anywhere the user can look, so the error is
+            // put down to the last override that turned a parsing document into one that doesn't
+            let broken_by = (1..documents.len()).rev()
+                .find(|&index| parse(&documents[index - 1]).is_ok());
+            match broken_by {
+                Some(index) => RehndaError::InvalidOverride {
+                    assignment: overrides[index - 1].clone(),
+                    message: format!("invalid value: {}", error.code),
+                },
+                None => RehndaError::from_ron(path.to_path_buf(), error),
+            }
+        })?;
         info!("Loaded settings: {:?}", settings);
-        settings
+        if settings.path_termination == PathTermination::MaxDepth && settings.max_depth.is_none() {
+            return Err(RehndaError::InvalidSettings("max_depth must be set when path_termination is MaxDepth".to_string()));
+        }
+        Ok(settings)
+    }
+
+    /// Hash of the scene file and the settings that change the rendered radiance. Renders with the
+    /// same hash converge to the same image, whatever their seeds, sample counts or outputs, so
+    /// their checkpoints can be merged.
+    pub fn render_hash(&self) -> Result<u64, RehndaError> {
+        let scene_data = fs::read(&self.scene_file)
+            .map_err(|source| RehndaError::Read { path: self.scene_file.clone().into(), source })?;
+        let render_settings = format!("{:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?}", self.max_depth, self.path_termination, self.integrator, self.sampler,
+                                      self.filter, self.resolution(), self.camera_settings, self.sky);
+        Ok(hash_u64(hash_bytes(&scene_data) ^ hash_bytes(render_settings.as_bytes())))
     }
 
     pub fn aspect_ratio(&self) -> f32 {
         self.camera_settings.aspect_ratio()
     }
 
-    pub fn num_threads(&self) -> usize {
-        self.num_threads.unwrap_or(thread::available_parallelism().map(NonZeroUsize::get).unwrap_or(1))
+    pub fn scene_options(&self) -> SceneOptions {
+        SceneOptions {
+            aspect_ratio: self.aspect_ratio(),
+            aperture: self.camera_settings.aperture,
+            acceleration_settings: self.acceleration_settings.clone(),
+            seed: self.seed,
+            sky: self.sky.clone(),
+        }
+    }
+
+    pub fn aggregation_config(&self) -> AggregationConfig {
+        AggregationConfig {
+            samples_per_pixel: self.num_samples,
+            path_settings: PathSettings {
+                max_depth: self.max_depth,
+                termination: self.path_termination,
+            },
+            integrator: self.integrator,
+            sampler: self.sampler,
+            filter: self.filter,
+            crop_window: self.crop_window,
+            adaptive_sampling: self.adaptive_sampling,
+            progressive: self.progressive,
+            seed: self.seed,
+        }
     }
 
-    pub fn num_samples_per_thread(&self) -> usize {
-        self.num_samples / self.num_threads()
+    pub fn num_threads(&self) -> usize {
+        self.num_threads.unwrap_or_else(available_threads)
     }
 
-    pub fn image_height(&self) -> usize {
-        (self.image_width as f32 / self.aspect_ratio()) as usize
+    /// Width and height of the rendered image in pixels, after scaling by `preview_scale`
+    pub fn resolution(&self) -> (usize, usize) {
+        let image_width = (self.image_width as f32 * self.preview_scale).round() as usize;
+        (image_width, (image_width as f32 / self.aspect_ratio()) as usize)
     }
+}
+
+fn default_preview_scale() -> f32 {
+    1.0
 }
\ No newline at end of file
@@ -0,0 +1,333 @@
+/// Short names for commonly swept settings
+const ALIASES: [(&str, &str); 7] = [
+    ("samples", "num_samples"),
+    ("width", "image_width"),
+    ("scene", "scene_file"),
+    ("output", "output_file"),
+    ("threads", "num_threads"),
+    ("aperture", "camera_settings.aperture"),
+    ("exposure", "display_settings.exposure"),
+];
+
+/// Settings holding file paths, whose values are quoted unless they already are or are `None`
+const STRING_FIELDS: [&str; 3] = ["scene_file", "output_file", "checkpoint_file"];
+
+/// Applies a `key=value` override to a RON settings document, returning the edited document.
+///
+/// The key is a field name, or a path of field names separated by dots for fields of nested
+/// structs (e.g. `camera_settings.aperture`), and the value is written in RON. Values of the file
+/// path settings and other values that aren't valid RON are taken as strings. The value replaces
+/// the field's value in the document, or the field is added if it isn't there, so the edited
+/// document is checked by the settings' deserialization like any other.
+pub fn apply_override(document: &str, assignment: &str) -> Result<String, String> {
+    let (key, value) = assignment.split_once('=')
+        .ok_or_else(|| format!("'{}' is not of the form key=value", assignment))?;
+    let key = key.trim();
+    let key = ALIASES.iter()
+        .find(|(alias, _)| *alias == key)
+        .map_or(key, |(_, field_path)| field_path);
+    let value = value.trim();
+    let is_string_field = STRING_FIELDS.contains(&key);
+    let value = match ron::from_str::<ron::Value>(value) {
+        // a bare word like `render` is valid RON, but as an identifier rather than a string
+        Ok(_) if is_string_field && !value.starts_with('"') && !value.starts_with("Some") && value != "None" => format!("{:?}", value),
+        Ok(_) => value.to_string(),
+        Err(_) => format!("{:?}", value),
+    };
+
+    let path: Vec<&str> = key.split('.').collect();
+    let mut document = document.to_string();
+    let document_end = document.len();
+    set_field(&mut document, 0, document_end, &path, &value)?;
+    Ok(document)
+}
+
+/// Sets the field at `path` within the struct value spanning `start..end` of the document
+fn set_field(document: &mut String, start: usize, end: usize, path: &[&str], value: &str) -> Result<(), String> {
+    let (body_start, body_end) = struct_body(document, start, end)
+        .ok_or_else(|| format!("no struct to set '{}' in", path[0]))?;
+    let fields = struct_fields(document, body_start, body_end);
+
+    match fields.iter().find(|field| field.name == path[0]) {
+        Some(field) if path.len() == 1 => {
+            document.replace_range(field.value_start..field.value_end, value);
+            Ok(())
+        }
+        // an optional struct that isn't set yet is set to one with just the one field
+        Some(field) if &document[field.value_start..field.value_end] == "None" => {
+            document.replace_range(field.value_start..field.value_end, &format!("Some({})", nested_struct(&path[1..], value)));
+            Ok(())
+        }
+        Some(field) => set_field(document, field.value_start, field.value_end, &path[1..], value),
+        None => {
+            // missing structs along the path are created with just the one field, without a `Some`
+            // as the settings are parsed with implicit `Some`s and the struct may not be optional
+            let nested_value = nested_struct(&path[1..], value);
+            let (insert_at, separator) = match fields.last() {
+                Some(field) if field.has_trailing_comma => (field.comma_end, " "),
+                Some(field) => (field.value_end, ", "),
+                None => (body_start, ""),
+            };
+            document.insert_str(insert_at, &format!("{}{}: {}", separator, path[0], nested_value));
+            Ok(())
+        }
+    }
+}
+
+/// Value of nested structs each holding the next field of `path`, down to `value`
+fn nested_struct(path: &[&str], value: &str) -> String {
+    path.iter().rev().fold(value.to_string(), |inner, name| format!("({}: {})", name, inner))
+}
+
+struct Field {
+    name: String,
+    value_start: usize,
+    value_end: usize,
+    has_trailing_comma: bool,
+    comma_end: usize,
+}
+
+/// Span between the parentheses of a struct value within `start..end`, looking through an
+/// optional struct name and `Some(..)` wrapper
+fn struct_body(document: &str, start: usize, end: usize) -> Option<(usize, usize)> {
+    let bytes = document.as_bytes();
+    let mut position = skip_trivia(document, start, end);
+    let name_start = position;
+    while position < end && (bytes[position].is_ascii_alphanumeric() || bytes[position] == b'_') {
+        position += 1;
+    }
+    let name = &document[name_start..position];
+    position = skip_trivia(document, position, end);
+    if position >= end || bytes[position] != b'(' {
+        return None;
+    }
+    let close = matching_close(document, position, end)?;
+    if name == "Some" {
+        return struct_body(document, position + 1, close);
+    }
+    Some((position + 1, close))
+}
+
+/// Fields of the struct body spanning `start..end`, with their values trimmed of surrounding whitespace
+fn struct_fields(document: &str, start: usize, end: usize) -> Vec<Field> {
+    let bytes = document.as_bytes();
+    let mut fields = Vec::new();
+    let mut position = skip_trivia(document, start, end);
+    while position < end {
+        let name_start = position;
+        while position < end && (bytes[position].is_ascii_alphanumeric() || bytes[position] == b'_') {
+            position += 1;
+        }
+        let name = &document[name_start..position];
+        position = skip_trivia(document, position, end);
+        if name.is_empty() || position >= end || bytes[position] != b':' {
+            break;
+        }
+        let value_start = skip_trivia(document, position + 1, end);
+        let value_end = value_end(document, value_start, end);
+        position = skip_trivia(document, value_end, end);
+        let has_trailing_comma = position < end && bytes[position] == b',';
+        if has_trailing_comma {
+            position += 1;
+        }
+        fields.push(Field { name: name.to_string(), value_start, value_end, has_trailing_comma, comma_end: position });
+        position = skip_trivia(document, position, end);
+    }
+    fields
+}
+
+/// End of the value starting at `start`, just after its last character before the next comma or
+/// the end of the enclosing struct
+fn value_end(document: &str, start: usize, end: usize) -> usize {
+    let bytes = document.as_bytes();
+    let mut position = start;
+    let mut last_significant = start;
+    while position < end {
+        match bytes[position] {
+            b',' => break,
+            b'(' | b'[' | b'{' => {
+                position = matching_close(document, position, end).map_or(end, |close| close + 1);
+                last_significant = position;
+            }
+            b'"' => {
+                position = string_end(document, position, end);
+                last_significant = position;
+            }
+            b'/' if bytes.get(position + 1).is_some_and(|next| *next == b'/' || *next == b'*') => {
+                position = skip_trivia(document, position, end);
+            }
+            byte if byte.is_ascii_whitespace() => position += 1,
+            _ => {
+                position += 1;
+                last_significant = position;
+            }
+        }
+    }
+    last_significant
+}
+
+/// Index of the bracket closing the one at `open`, skipping over strings and comments
+fn matching_close(document: &str, open: usize, end: usize) -> Option<usize> {
+    let bytes = document.as_bytes();
+    let mut depth = 0;
+    let mut position = open;
+    while position < end {
+        match bytes[position] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(position);
+                }
+            }
+            b'"' => {
+                position = string_end(document, position, end);
+                continue;
+            }
+            b'/' if bytes.get(position + 1).is_some_and(|next| *next == b'/' || *next == b'*') => {
+                position = skip_trivia(document, position, end);
+                continue;
+            }
+            _ => {}
+        }
+        position += 1;
+    }
+    None
+}
+
+/// Index just past the string starting with the quote at `start`
+fn string_end(document: &str, start: usize, end: usize) -> usize {
+    let bytes = document.as_bytes();
+    let mut position = start + 1;
+    while position < end {
+        match bytes[position] {
+            b'\\' => position += 2,
+            b'"' => return position + 1,
+            _ => position += 1,
+        }
+    }
+    end
+}
+
+/// Index of the first character from `start` that isn't whitespace or part of a comment
+fn skip_trivia(document: &str, start: usize, end: usize) -> usize {
+    let bytes = document.as_bytes();
+    let mut position = start;
+    while position < end {
+        if bytes[position].is_ascii_whitespace() {
+            position += 1;
+        } else if document[position..end].starts_with("//") {
+            position = document[position..end].find('\n').map_or(end, |newline| position + newline + 1);
+        } else if document[position..end].starts_with("/*") {
+            position = document[position + 2..end].find("*/").map_or(end, |close| position + 2 + close + 2);
+        } else {
+            break;
+        }
+    }
+    position
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::*;
+    use crate::error::RehndaError;
+    use crate::scene::settings::RehndaSettings;
+
+    const SETTINGS: &str = r#"RehndaSettings(
+    scene_file: "scenes/a, (b).ron", // commas, (parentheses) and "quotes" in comments
+    output_file: None,
+    /* block comment, with a ( */ image_width: 600,
+    num_samples: 16,
+    progressive: None,
+    camera_settings: (
+        aspect_ratio: (width: 16.0, height: 9.0),
+        aperture: 0.0,
+    ),
+)"#;
+
+    #[test]
+    fn replaces_top_level_fields() {
+        let document = apply_override(SETTINGS, "image_width=320").unwrap();
+        assert!(document.contains("image_width: 320,"));
+        assert!(document.contains("num_samples: 16,"));
+    }
+
+    #[test]
+    fn replaces_nested_fields() {
+        let document = apply_override(SETTINGS, "camera_settings.aperture=0.5").unwrap();
+        assert!(document.contains("aperture: 0.5,"));
+        let document = apply_override(SETTINGS, "camera_settings.aspect_ratio.height=10.0").unwrap();
+        assert!(document.contains("aspect_ratio: (width: 16.0, height: 10.0),"));
+    }
+
+    #[test]
+    fn replaces_tuples_and_options() {
+        let document = apply_override(SETTINGS, "camera_settings.aspect_ratio=(width: 1.0, height: 1.0)").unwrap();
+        assert!(document.contains("aspect_ratio: (width: 1.0, height: 1.0),"));
+        let document = apply_override(SETTINGS, "progressive=Some((checkpoint_passes: Some(2)))").unwrap();
+        assert!(document.contains("progressive: Some((checkpoint_passes: Some(2))),"));
+    }
+
+    #[test]
+    fn skips_strings_and_comments() {
+        let document = apply_override(SETTINGS, "output_file=out.png").unwrap();
+        assert!(document.contains(r#"scene_file: "scenes/a, (b).ron", // commas"#));
+        assert!(document.contains(r#"output_file: "out.png","#));
+        let document = apply_override(SETTINGS, "image_width=100").unwrap();
+        assert!(document.contains("/* block comment, with a ( */ image_width: 100,"));
+    }
+
+    #[test]
+    fn adds_missing_fields_and_structs() {
+        let document = apply_override(SETTINGS, "seed=7").unwrap();
+        assert!(document.contains("seed: 7"));
+        let document = apply_override(SETTINGS, "display_settings.exposure=1.5").unwrap();
+        assert!(document.contains("display_settings: (exposure: 1.5)"));
+    }
+
+    #[test]
+    fn sets_fields_of_optional_structs_that_are_none() {
+        let document = apply_override(SETTINGS, "progressive.checkpoint_seconds=5").unwrap();
+        assert!(document.contains("progressive: Some((checkpoint_seconds: 5)),"));
+    }
+
+    #[test]
+    fn resolves_aliases() {
+        let document = apply_override(SETTINGS, "samples=64").unwrap();
+        assert!(document.contains("num_samples: 64,"));
+        let document = apply_override(SETTINGS, "aperture=0.1").unwrap();
+        assert!(document.contains("aperture: 0.1,"));
+    }
+
+    #[test]
+    fn quotes_file_paths() {
+        assert!(apply_override(SETTINGS, "output=render").unwrap().contains(r#"output_file: "render","#));
+        assert!(apply_override(SETTINGS, r#"scene="x.ron""#).unwrap().contains(r#"scene_file: "x.ron","#));
+        assert!(apply_override(SETTINGS, "output_file=None").unwrap().contains("output_file: None,"));
+        assert!(apply_override(SETTINGS, "checkpoint_file=run.ckpt").unwrap().contains(r#"checkpoint_file: "run.ckpt""#));
+    }
+
+    #[test]
+    fn rejects_malformed_assignments() {
+        assert_eq!(apply_override(SETTINGS, "image_width").unwrap_err(), "'image_width' is not of the form key=value");
+        assert_eq!(apply_override(SETTINGS, "image_width.x=1").unwrap_err(), "no struct to set 'x' in");
+    }
+
+    #[test]
+    fn reports_unknown_keys_and_bad_values_against_the_override() {
+        let path = std::env::temp_dir().join(format!("rehnda_settings_override_{}.ron", std::process::id()));
+        fs::write(&path, SETTINGS).unwrap();
+        let error_message = |assignment: &str| match RehndaSettings::from_file(&path, &[assignment.to_string()]) {
+            Err(error @ RehndaError::InvalidOverride { .. }) => error.to_string(),
+            other => panic!("expected an invalid override error, got {:?}", other.map(|_| ())),
+        };
+        let unknown_key = error_message("image_widht=10");
+        let bad_value = error_message("samples=abc");
+        fs::remove_file(&path).unwrap();
+
+        assert!(unknown_key.starts_with("invalid --set image_widht=10: invalid value: "), "{}", unknown_key);
+        assert!(unknown_key.contains("image_widht"), "{}", unknown_key);
+        assert_eq!(bad_value, "invalid --set samples=abc: invalid value: Expected integer");
+    }
+}
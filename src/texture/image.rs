@@ -2,6 +2,7 @@ use std::path::Path;
 use image::RgbImage;
 use crate::core::color::ColorRgbF;
 use crate::core::rehnda_math::Point3f;
+use crate::error::RehndaError;
 use crate::texture::{Texture, Uv};
 
 pub struct ImageTexture {
@@ -9,13 +10,14 @@ pub struct ImageTexture {
 }
 
 impl ImageTexture {
-    pub fn new_from_image_file(image_path: &Path) -> ImageTexture {
-        let img = image::open(image_path).unwrap();
+    pub fn new_from_image_file(image_path: &Path) -> Result<ImageTexture, RehndaError> {
+        let img = image::open(image_path)
+            .map_err(|source| RehndaError::Texture { path: image_path.to_path_buf(), source })?;
         let rgb_img = img.to_rgb8();
 
-        ImageTexture {
+        Ok(ImageTexture {
             rgb_img
-        }
+        })
     }
 }
 
@@ -1,14 +1,24 @@
 use crate::core::color::ColorRgbF;
-use crate::core::rehnda_math::Point3f;
+use crate::core::rehnda_math::{Point3f, Rng};
 use crate::texture::{Texture, Uv};
-use crate::util::perlin::PERLIN;
+use crate::util::perlin::Perlin;
 
 pub struct NoiseTexture {
     pub scale: f32,
+    perlin: Perlin,
+}
+
+impl NoiseTexture {
+    pub fn new(scale: f32, rng: &mut Rng) -> NoiseTexture {
+        NoiseTexture {
+            scale,
+            perlin: Perlin::new(rng),
+        }
+    }
 }
 
 impl Texture for NoiseTexture {
     fn sample(&self, uv: &Uv, point: &Point3f) -> ColorRgbF {
-        ColorRgbF::ONE * 0.5 * (1.0 + (self.scale * point.z + 10.0 * PERLIN.turbulence(point, 7)).sin())
+        ColorRgbF::ONE * 0.5 * (1.0 + (self.scale * point.z + 10.0 * self.perlin.turbulence(point, 7)).sin())
     }
 }
\ No newline at end of file
@@ -1,10 +1,6 @@
-use lazy_static::lazy_static;
-use crate::core::rehnda_math::{Point3f, random_int_in_range, Vec3Ext, Vec3f};
+use crate::core::rehnda_math::{Point3f, random_int_in_range, Rng, Vec3Ext, Vec3f};
 
 const POINT_COUNT: usize = 256;
-lazy_static! {
-    pub static ref PERLIN: Perlin = Perlin::new();
-}
 
 pub struct Perlin {
     random_vecs: Vec<Vec3f>,
@@ -14,17 +10,17 @@ pub struct Perlin {
 }
 
 impl Perlin {
-    fn new() -> Perlin {
+    pub fn new(rng: &mut Rng) -> Perlin {
         let mut random_vecs = Vec::with_capacity(POINT_COUNT);
         for _ in 0..POINT_COUNT {
-            random_vecs.push(Vec3f::random_vec_in_range(-1.0, 1.0));
+            random_vecs.push(Vec3f::random_vec_in_range(rng, -1.0, 1.0));
         }
 
         Perlin {
             random_vecs,
-            perm_x: perlin_generate_perm(),
-            perm_y: perlin_generate_perm(),
-            perm_z: perlin_generate_perm(),
+            perm_x: perlin_generate_perm(rng),
+            perm_y: perlin_generate_perm(rng),
+            perm_z: perlin_generate_perm(rng),
         }
     }
 
@@ -84,14 +80,14 @@ fn perlin_interp(c: &[[[Vec3f; 2]; 2]; 2], u: f32, v: f32, w: f32) -> f32 {
     accum
 }
 
-fn perlin_generate_perm() -> [i32; POINT_COUNT] {
+fn perlin_generate_perm(rng: &mut Rng) -> [i32; POINT_COUNT] {
     let mut perm: [i32; POINT_COUNT] = [0; POINT_COUNT];
     for (i, el) in &mut perm.iter_mut().enumerate() {
         *el = i as i32;
     }
 
     for i in 0..POINT_COUNT {
-        let target = random_int_in_range(0, i as i32) as usize;
+        let target = random_int_in_range(rng, 0, i as i32) as usize;
         perm.swap(i, target);
     }
 